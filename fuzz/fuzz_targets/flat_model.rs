@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use model_reader::flat_model::FlatModel;
+use model_reader::{load_any, LoadOptions};
+use std::io::{sink, Cursor};
+
+// converters only promise not to panic on models that validate,
+// so strict loading filters out the rest
+fuzz_target!(|data: &[u8]| {
+    let options = LoadOptions {
+        strict: true,
+        ..Default::default()
+    };
+    if let Ok(model) = model_reader::load_any_with(&mut Cursor::new(data), &options) {
+        let flat = FlatModel::from_model(&model);
+        flat.write_json(&mut sink()).unwrap();
+    }
+    let _ = load_any(&mut Cursor::new(data));
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use model_reader::flat_model::FlatModel;
+use model_reader::mdl;
+use std::io::{sink, Cursor};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(model) = mdl::Model::from_reader(&mut Cursor::new(data)) {
+        if model.validate().is_empty() {
+            model.write_json(&mut sink()).unwrap();
+            FlatModel::from_mdl(&model).write_json(&mut sink()).unwrap();
+        }
+    }
+});
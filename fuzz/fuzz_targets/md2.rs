@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use model_reader::md2;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(model) = md2::Model::from_reader(&mut Cursor::new(data)) {
+        let _ = model.validate();
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use model_reader::pcx::Pcx;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(pcx) = Pcx::from_reader(&mut Cursor::new(data)) {
+        let _ = pcx.to_rgba();
+    }
+});
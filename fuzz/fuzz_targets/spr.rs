@@ -0,0 +1,15 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use model_reader::palette::Palette;
+use model_reader::spr;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(sprite) = spr::Sprite::from_reader(&mut Cursor::new(data)) {
+        let _ = sprite.validate();
+        let palette = Palette::default();
+        for idx in 0..sprite.frames.len() {
+            let _ = sprite.rgba_at(idx, 0.5, &palette);
+        }
+    }
+});
@@ -0,0 +1,418 @@
+use super::{md2, mdl, normalize, vec3_t, AliasModel};
+
+/// a named range of frames. first and last are
+/// frame indices, both inclusive
+pub struct Clip {
+    pub name: String,
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+}
+
+impl Clip {
+    /// 0 for a clip whose first frame comes after its last
+    pub fn num_frames(&self) -> usize {
+        (self.last + 1).saturating_sub(self.first)
+    }
+
+    /// the two frames and the blend factor between them at time
+    /// seconds into the clip. the clip loops, after the last
+    /// frame it blends back to the first one
+    pub fn frames_at(&self, time: f32) -> (usize, usize, f32) {
+        if self.first >= self.last || self.fps <= 0.0 || !time.is_finite() {
+            return (self.first, self.first, 0.0);
+        }
+
+        let num_frames = self.num_frames();
+        let position = (time * self.fps).rem_euclid(num_frames as f32);
+        // rem_euclid may round up to num_frames
+        let k = (position.floor() as usize).min(num_frames - 1);
+        let blend = (position - k as f32).clamp(0.0, 1.0);
+        let a = self.first + k;
+        let b = self.first + (k + 1) % num_frames;
+        (a, b, blend)
+    }
+}
+
+/// how an Animator plays a clip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    /// after the last frame it blends back to the first one
+    Loop,
+    /// stops at the last frame
+    Once,
+    /// plays forwards, then backwards
+    PingPong,
+}
+
+/// reported by Animator::update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// a Loop or PingPong clip is back at its first frame
+    Looped { clip: usize },
+    /// a Once clip reached its last frame and holds it
+    Finished { clip: usize },
+}
+
+/// a clip being played
+#[derive(Debug, Clone, Copy)]
+struct Playback {
+    clip: usize,
+    mode: PlayMode,
+    time: f32,
+    finished: bool,
+}
+
+/// a clip that is faded out
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    from: Playback,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// plays the clips of a model. update() advances the time,
+/// pose() samples the model for the current frames
+pub struct Animator {
+    clips: Vec<Clip>,
+    current: Option<Playback>,
+    fade: Option<Fade>,
+    offset: f32,
+}
+
+impl Animator {
+    /// nothing is played until play() or crossfade() is called
+    pub fn new(clips: Vec<Clip>) -> Self {
+        Animator {
+            clips: clips,
+            current: None,
+            fade: None,
+            offset: 0.0,
+        }
+    }
+
+    /// honours the synctype of an mdl. with ST_RAND every clip starts
+    /// at random * its duration, so that monsters of the same kind do
+    /// not move in lockstep. random is in 0..1 and comes from the caller
+    pub fn for_mdl(clips: Vec<Clip>, model: &mdl::Model, random: f32) -> Self {
+        let mut animator = Self::new(clips);
+        if model.header.synctype == mdl::ST_RAND {
+            animator.offset = random.clamp(0.0, 1.0);
+        }
+        animator
+    }
+
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    /// index of the first clip called name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+
+    /// index of the clip being played
+    pub fn clip(&self) -> Option<usize> {
+        self.current.map(|current| current.clip)
+    }
+
+    /// seconds since the current clip started
+    pub fn time(&self) -> f32 {
+        self.current.map_or(0.0, |current| current.time)
+    }
+
+    /// true once a Once clip reached its last frame
+    pub fn finished(&self) -> bool {
+        matches!(self.current, Some(Playback { finished: true, .. }))
+    }
+
+    /// starts clip right away. panics if clip is not an index of clips()
+    pub fn play(&mut self, clip: usize, mode: PlayMode) {
+        self.fade = None;
+        self.current = Some(self.start(clip, mode));
+    }
+
+    /// starts clip and blends from the current pose to it over duration
+    /// seconds. the old clip keeps playing while it fades out
+    pub fn crossfade(&mut self, clip: usize, mode: PlayMode, duration: f32) {
+        let next = self.start(clip, mode);
+        self.fade = match self.current {
+            Some(from) if duration > 0.0 => Some(Fade {
+                from: from,
+                elapsed: 0.0,
+                duration: duration,
+            }),
+            _ => None,
+        };
+        self.current = Some(next);
+    }
+
+    fn start(&self, clip: usize, mode: PlayMode) -> Playback {
+        let cycle = cycle(&self.clips[clip], mode);
+        Playback {
+            clip: clip,
+            mode: mode,
+            time: self.offset * cycle,
+            finished: false,
+        }
+    }
+
+    /// advances the time by dt seconds. returns the ends of
+    /// the current clip that were passed
+    pub fn update(&mut self, dt: f32) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some(fade) = &mut self.fade {
+            advance(&self.clips[fade.from.clip], &mut fade.from, dt);
+            fade.elapsed += dt;
+            if fade.elapsed >= fade.duration {
+                self.fade = None;
+            }
+        }
+        if let Some(current) = &mut self.current {
+            if let Some(event) = advance(&self.clips[current.clip], current, dt) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// frames and blend factor of the current clip, see AliasModel::pose
+    pub fn frames(&self) -> Option<(usize, usize, f32)> {
+        self.current
+            .map(|current| frames(&self.clips[current.clip], &current))
+    }
+
+    /// samples the current pose. sample is given two frames and a blend
+    /// factor, like AliasModel::pose or FlatModel::pose:
+    /// animator.pose(|a, b, blend| model.pose(a, b, blend))
+    pub fn pose<F: Fn(usize, usize, f32) -> Pose>(&self, sample: F) -> Option<Pose> {
+        let (a, b, blend) = self.frames()?;
+        let pose = sample(a, b, blend);
+        match &self.fade {
+            Some(fade) => {
+                let (a, b, blend) = frames(&self.clips[fade.from.clip], &fade.from);
+                let from = sample(a, b, blend);
+                Some(from.lerp(&pose, fade.elapsed / fade.duration))
+            }
+            None => Some(pose),
+        }
+    }
+}
+
+/// seconds until a clip ends (Once) or starts over
+fn cycle(clip: &Clip, mode: PlayMode) -> f32 {
+    if clip.num_frames() == 0 || clip.fps <= 0.0 {
+        return 0.0;
+    }
+    let steps = match mode {
+        PlayMode::Loop => clip.num_frames(),
+        PlayMode::Once => clip.num_frames() - 1,
+        PlayMode::PingPong => 2 * (clip.num_frames() - 1),
+    };
+    steps as f32 / clip.fps
+}
+
+fn advance(clip: &Clip, playback: &mut Playback, dt: f32) -> Option<Event> {
+    let before = playback.time;
+    playback.time += dt;
+    let cycle = cycle(clip, playback.mode);
+
+    match playback.mode {
+        PlayMode::Once => {
+            if !playback.finished && playback.time >= cycle {
+                playback.finished = true;
+                return Some(Event::Finished {
+                    clip: playback.clip,
+                });
+            }
+        }
+        PlayMode::Loop | PlayMode::PingPong => {
+            if cycle > 0.0 && (playback.time / cycle).floor() > (before / cycle).floor() {
+                return Some(Event::Looped {
+                    clip: playback.clip,
+                });
+            }
+        }
+    }
+    None
+}
+
+fn frames(clip: &Clip, playback: &Playback) -> (usize, usize, f32) {
+    let cycle = cycle(clip, playback.mode);
+    if cycle <= 0.0 || clip.first >= clip.last {
+        return (clip.first, clip.first, 0.0);
+    }
+
+    let last = (clip.num_frames() - 1) as f32;
+    match playback.mode {
+        PlayMode::Loop => clip.frames_at(playback.time),
+        PlayMode::Once => {
+            let position = (playback.time * clip.fps).clamp(0.0, last);
+            let k = position.floor() as usize;
+            let a = clip.first + k;
+            (a, (a + 1).min(clip.last), position - k as f32)
+        }
+        PlayMode::PingPong => {
+            let position = (playback.time * clip.fps).rem_euclid(2.0 * last);
+            if position < last {
+                let k = position.floor() as usize;
+                (clip.first + k, clip.first + k + 1, position - k as f32)
+            } else {
+                let back = (position - last).min(last);
+                let k = (back.floor() as usize).min(clip.num_frames() - 2);
+                (
+                    clip.last - k,
+                    clip.last - k - 1,
+                    (back - k as f32).clamp(0.0, 1.0),
+                )
+            }
+        }
+    }
+}
+
+/// positions and normals of a model at some point of an animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub positions: Vec<vec3_t>,
+    pub normals: Vec<vec3_t>,
+}
+
+impl Pose {
+    /// linear blend towards other. blend 0 gives self, 1 gives
+    /// other. normals are renormalised
+    pub fn lerp(&self, other: &Pose, blend: f32) -> Pose {
+        lerp(
+            &self.positions,
+            &self.normals,
+            &other.positions,
+            &other.normals,
+            blend,
+        )
+    }
+}
+
+/// blends the positions and normals of two frames
+pub(crate) fn lerp(
+    positions_a: &[vec3_t],
+    normals_a: &[vec3_t],
+    positions_b: &[vec3_t],
+    normals_b: &[vec3_t],
+    blend: f32,
+) -> Pose {
+    let mix = |a: &vec3_t, b: &vec3_t| {
+        [
+            a[0] + (b[0] - a[0]) * blend,
+            a[1] + (b[1] - a[1]) * blend,
+            a[2] + (b[2] - a[2]) * blend,
+        ]
+    };
+
+    let positions = positions_a
+        .iter()
+        .zip(positions_b)
+        .map(|(a, b)| mix(a, b))
+        .collect();
+    // opposite normals cancel out half way, take the nearer one then
+    let normals = normals_a
+        .iter()
+        .zip(normals_b)
+        .map(|(a, b)| match normalize(mix(a, b)) {
+            Some(normal) => normal,
+            None if blend < 0.5 => *a,
+            None => *b,
+        })
+        .collect();
+
+    Pose {
+        positions: positions,
+        normals: normals,
+    }
+}
+
+/// splits the trailing number off a frame name
+fn split_number(name: &str) -> (&str, Option<u32>) {
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (base, name[base.len()..].parse().ok())
+}
+
+/// splits the trailing frame counter off a frame name. numbers with
+/// three or more digits keep all but the last two digits in the name,
+/// so "pain101" is frame 1 of "pain1" while "stand01" is frame 1 of
+/// "stand". a leading zero is padding, "frame005" is frame 5 of "frame"
+fn split_counter(name: &str) -> (&str, Option<u32>) {
+    let (base, number) = split_number(name);
+    let digits = &name[base.len()..];
+    if digits.len() >= 3 && !digits.starts_with('0') {
+        let split = name.len() - 2;
+        (&name[..split], name[split..].parse().ok())
+    } else {
+        (base, number)
+    }
+}
+
+/// groups runs of frames whose names only differ in a trailing
+/// frame counter, like "run1".."run6" or "stand01".."stand40", into
+/// clips named after the common part. see split_counter for names
+/// like "pain101".."pain304". a frame numbered one more than the one
+/// before always continues the clip, so "run98".."run101" is one clip.
+/// otherwise a counter that does not increase starts a new clip.
+/// every clip plays at fps
+pub fn clips_from_names(names: &[&str], fps: f32) -> Vec<Clip> {
+    let mut clips = Vec::<Clip>::new();
+    let mut last_number: Option<(&str, u32)> = None;
+    let mut last_counter = None;
+    for (frame, name) in names.iter().enumerate() {
+        let (base, counter) = split_counter(name);
+        let (whole, number) = split_number(name);
+        let follows = match (last_number, number) {
+            (Some((last_whole, last)), Some(number)) => {
+                last_whole == whole && last.checked_add(1) == Some(number)
+            }
+            _ => false,
+        };
+        let restarted = match (last_counter, counter) {
+            (Some(last), Some(counter)) => counter <= last,
+            _ => false,
+        };
+        match clips.last_mut() {
+            Some(clip) if follows => {
+                // the run crossed the group digits, like "run99" to "run100"
+                if clip.name != base {
+                    clip.name = whole.to_string();
+                }
+                clip.last = frame;
+            }
+            Some(clip) if clip.name == base && !restarted => clip.last = frame,
+            _ => clips.push(Clip {
+                name: base.to_string(),
+                first: frame,
+                last: frame,
+                fps: fps,
+            }),
+        }
+        last_number = number.map(|number| (whole, number));
+        last_counter = counter;
+    }
+    clips
+}
+
+/// clips_from_names for the frames of an md2, mdl or md3
+pub fn clips_from_frames(model: &dyn AliasModel, fps: f32) -> Vec<Clip> {
+    clips_from_names(&model.frame_names(), fps)
+}
+
+/// md2::ANIMATIONS as clips. an optional preset that is only
+/// meaningful for the stock quake 2 player models, see
+/// clips_from_frames for any other model
+pub fn md2_clips() -> Vec<Clip> {
+    md2::ANIMATIONS
+        .iter()
+        .zip(md2::ANIMATION_NAMES.iter())
+        .map(|(anim, name)| Clip {
+            name: name.to_string(),
+            first: anim[0] as usize,
+            last: anim[1] as usize,
+            fps: anim[2] as f32,
+        })
+        .collect()
+}
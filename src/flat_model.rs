@@ -1,11 +1,24 @@
-use super::vec3_t;
+use super::animation::{self, Clip, Pose};
+use super::mikktspace;
+use super::{add, cross, dot, normalize, scale, sub, vec3_t, AliasModel};
+use std::collections::HashMap;
 use std::io::Write;
 
+/// how the normals of the faces around a vertex are weighted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// by the area of the face
+    Area,
+    /// by the angle of the face at the vertex
+    Angle,
+}
+
 pub struct FlatModel {
     pub vertices: Vec<Vec<vec3_t>>, // list of frames. each frame has same length is a list of vec3_t
+    pub normals: Vec<Vec<vec3_t>>,  // same layout as vertices
+    pub tangents: Vec<Vec<[f32; 4]>>, // same layout as vertices. see generate_tangents
     pub texcoords: Vec<(f32, f32)>, // should have the same langth as any of the frames
     pub indices: Vec<(usize, usize, usize)>, // basicaly a triangle
-    // normals?
 }
 
 impl FlatModel {
@@ -34,18 +47,32 @@ impl FlatModel {
     }
 
     fn write_frame(&self, writer: &mut dyn Write, idx: usize) -> Result<(), std::io::Error> {
-        write!(writer, "{{\n\t\t\"vertices\": [\n")?;
+        write!(writer, "{{\n\t\t\"vertices\": [")?;
         let vertices = &self.vertices[idx];
 
-        let x: f32 = vertices[0][0];
-        let y: f32 = vertices[0][1];
-        let z: f32 = vertices[0][2];
-        write!(writer, "\t\t\t{}, {}, {}", x, y, z)?;
-        for i in 1..vertices.len() {
+        for i in 0..vertices.len() {
             let x: f32 = vertices[i][0];
             let y: f32 = vertices[i][1];
             let z: f32 = vertices[i][2];
-            write!(writer, ",\n\t\t\t{}, {}, {}", x, y, z)?;
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t\t{}, {}, {}", sep, x, y, z)?;
+        }
+        write!(writer, "\n\t\t],\n\t\t\"normals\": [")?;
+
+        // a model built by hand may come without normals
+        let normals = self.normals.get(idx).map_or(&[][..], |n| &n[..]);
+        for i in 0..normals.len() {
+            let [x, y, z] = normals[i];
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t\t{}, {}, {}", sep, x, y, z)?;
+        }
+        write!(writer, "\n\t\t],\n\t\t\"tangents\": [")?;
+
+        let tangents = self.tangents.get(idx).map_or(&[][..], |t| &t[..]);
+        for i in 0..tangents.len() {
+            let [x, y, z, w] = tangents[i];
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t\t{}, {}, {}, {}", sep, x, y, z, w)?;
         }
         write!(writer, "\n\t\t]\n\t}}")?;
         Ok(())
@@ -54,59 +81,48 @@ impl FlatModel {
     fn write_faces(&self, writer: &mut dyn Write) -> Result<(), std::io::Error> {
         let indices = &self.indices;
 
-        let (a, b, c) = indices[0];
-        write!(writer, "\n\t\"indices\": [\n\t\t{}, {}, {}", a, b, c)?;
-        for i in 1..indices.len() {
+        write!(writer, "\n\t\"indices\": [")?;
+        for i in 0..indices.len() {
             let (a, b, c) = indices[i];
-            write!(writer, ",\n\t\t{}, {}, {}", a, b, c)?;
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t{}, {}, {}", sep, a, b, c)?;
         }
         write!(writer, "\n\t],\n")?;
 
         let texcoords = &self.texcoords;
-        let (s, t) = texcoords[0];
-        write!(writer, "\t\"texcoords\": [\n\t\t{}, {}", s, t)?;
-        for i in 1..texcoords.len() {
+        write!(writer, "\t\"texcoords\": [")?;
+        for i in 0..texcoords.len() {
             let (s, t) = texcoords[i];
-            write!(writer, ",\n\t\t{}, {}", s, t)?;
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t{}, {}", sep, s, t)?;
         }
         write!(writer, "\n\t]\n")?;
         Ok(())
     }
 
-    pub fn from_md2(model: &super::md2::Model) -> Self {
-        let w = model.header.skin_width as f32;
-        let h = model.header.skin_height as f32;
-
-        let mut vertices = Vec::<Vec<vec3_t>>::with_capacity(model.frames.len());
-        for frame in &model.frames {
-            let scale = frame.scale;
-            let translate = frame.translate;
-
-            let mut temp = Vec::<vec3_t>::new();
-
-            for vertex in &frame.vertices {
-                let x = (vertex.v[0] as f32 * scale[0]) + translate[0];
-                let y = (vertex.v[1] as f32 * scale[1]) + translate[1];
-                let z = (vertex.v[2] as f32 * scale[2]) + translate[2];
-                temp.push([x, y, z]);
-            }
-
-            vertices.push(temp);
+    /// flattens any AliasModel. a vertex used with different
+    /// texcoords is duplicated, so that positions, normals
+    /// and texcoords can share one index. indices of the model
+    /// are not checked, validate() it first if it is untrusted
+    pub fn from_model(model: &dyn AliasModel) -> Self {
+        let mut vertices = Vec::<Vec<vec3_t>>::with_capacity(model.num_frames());
+        let mut normals = Vec::<Vec<vec3_t>>::with_capacity(model.num_frames());
+        for frame in 0..model.num_frames() {
+            vertices.push(model.positions(frame));
+            normals.push(model.normals(frame));
         }
-        use std::collections::HashMap;
+
+        use std::collections::hash_map::Entry;
+        let model_texcoords = model.texcoords();
         let mut set = HashMap::<usize, HashMap<usize, usize>>::new();
         let mut indices = Vec::<usize>::new();
-        let mut texcoords = vec![(0f32, 0f32); vertices[0].len() * 2];
+        let mut texcoords = vec![(0f32, 0f32); model.num_vertices()];
 
-        for face in &model.faces {
+        for face in model.faces() {
             for i in 0..3 {
-                let vec_idx = face.vertex[i] as usize;
-                let tex_idx = face.st_idx[i] as usize;
-                let st = {
-                    let s = model.texcoords[tex_idx].s as f32 / w;
-                    let t = model.texcoords[tex_idx].t as f32 / h;
-                    (s, t)
-                };
+                let vec_idx = face.vertex[i];
+                let tex_idx = face.texcoord[i];
+                let st = model_texcoords[tex_idx];
                 /*
                 1) if the vertex (vec_idx) is new:
                 store vec_idx in indices
@@ -120,13 +136,13 @@ impl FlatModel {
                     store this position in indices and texcoords at this new position
 
                 */
-                if !set.contains_key(&vec_idx) {
+                if let Entry::Vacant(entry) = set.entry(vec_idx) {
                     // 1)
                     indices.push(vec_idx);
                     texcoords[vec_idx] = st;
                     let mut new_map = HashMap::new();
                     new_map.insert(tex_idx, vec_idx);
-                    set.insert(vec_idx, new_map);
+                    entry.insert(new_map);
                 } else {
                     if set[&vec_idx].contains_key(&tex_idx) {
                         // 2)
@@ -138,10 +154,14 @@ impl FlatModel {
                             let vertex = frame[vec_idx];
                             frame.push(vertex);
                         }
+                        for frame in &mut normals {
+                            let normal = frame[vec_idx];
+                            frame.push(normal);
+                        }
 
-                        let new_idx = vertices[0].len() - 1;
+                        let new_idx = texcoords.len();
                         indices.push(new_idx);
-                        texcoords[new_idx] = st;
+                        texcoords.push(st);
                         set.get_mut(&vec_idx).unwrap().insert(tex_idx, new_idx);
                     }
                 }
@@ -156,73 +176,230 @@ impl FlatModel {
             fi.push((a, b, c));
         }
 
-        texcoords.truncate(vertices[0].len());
         FlatModel {
             vertices: vertices,
+            normals: normals,
+            tangents: Vec::new(),
             indices: fi,
             texcoords: texcoords,
         }
     }
 
-    pub fn from_mdl(model: &super::mdl::Model) -> Self {
-        let scale = model.header.scale;
-        let translate = model.header.translate;
-        let w = model.header.skin_width as f32;
-        let h = model.header.skin_height as f32;
+    /// replaces the normals of every frame with smooth normals computed
+    /// from the triangles. vertices at the same position, like the seam
+    /// duplicates made by from_model, are welded and share one normal
+    pub fn recompute_normals(&mut self, weighting: NormalWeighting) {
+        let mut normals = Vec::with_capacity(self.vertices.len());
+        for frame in 0..self.vertices.len() {
+            normals.push(self.smooth_normals(frame, weighting));
+        }
+        self.normals = normals;
+    }
 
-        let mut vertices = Vec::<Vec<vec3_t>>::new();
+    fn smooth_normals(&self, frame: usize, weighting: NormalWeighting) -> Vec<vec3_t> {
+        let positions = &self.vertices[frame];
 
-        for frame in &model.frames {
-            let mut temp = Vec::<vec3_t>::with_capacity(model.header.num_verices as usize);
-            for vertex in &frame.frame.verts {
-                let x = ((vertex.v[0] as f32) * scale[0]) + translate[0];
-                let y = ((vertex.v[1] as f32) * scale[1]) + translate[1];
-                let z = ((vertex.v[2] as f32) * scale[2]) + translate[2];
+        // weld vertices with equal positions. adding 0.0 turns -0.0 into 0.0
+        let mut welded = HashMap::<[u32; 3], usize>::new();
+        let mut group = Vec::<usize>::with_capacity(positions.len());
+        for p in positions {
+            let key = [
+                (p[0] + 0.0).to_bits(),
+                (p[1] + 0.0).to_bits(),
+                (p[2] + 0.0).to_bits(),
+            ];
+            let next = welded.len();
+            group.push(*welded.entry(key).or_insert(next));
+        }
 
-                temp.push([x, y, z]);
+        let mut sums = vec![[0f32; 3]; welded.len()];
+        for &(a, b, c) in &self.indices {
+            let corners = [a, b, c];
+            let p = [positions[a], positions[b], positions[c]];
+            // quake models wind front faces clockwise, so
+            // (p2 - p0) x (p1 - p0) points outwards. its length
+            // is twice the area of the face
+            let face = cross(sub(p[2], p[0]), sub(p[1], p[0]));
+            match weighting {
+                NormalWeighting::Area => {
+                    for &v in &corners {
+                        sums[group[v]] = add(sums[group[v]], face);
+                    }
+                }
+                NormalWeighting::Angle => {
+                    let unit = match normalize(face) {
+                        Some(unit) => unit,
+                        None => continue,
+                    };
+                    for i in 0..3 {
+                        let e1 = normalize(sub(p[(i + 1) % 3], p[i]));
+                        let e2 = normalize(sub(p[(i + 2) % 3], p[i]));
+                        if let (Some(e1), Some(e2)) = (e1, e2) {
+                            let angle = dot(e1, e2).clamp(-1.0, 1.0).acos();
+                            let g = group[corners[i]];
+                            sums[g] = add(sums[g], scale(unit, angle));
+                        }
+                    }
+                }
             }
-            vertices.push(temp);
         }
-        let mut texcoords = vec![(0f32, 0f32); vertices[0].len() * 3];
-        let mut indices = Vec::<usize>::new();
 
-        for face in &model.triangles {
-            let is_back = face.facefront == 0;
-            for v in face.vertex.iter() {
-                let idx = *v as usize;
-                let onseam = model.texcoords[idx].onseam > 0;
-                if is_back && onseam {
-                    let s = (model.texcoords[idx].s as f32 + (w * 0.5f32) + 0.5) / w;
-                    let t = (model.texcoords[idx].t as f32 + 0.5) / h;
-                    for vertex in &mut vertices {
-                        let new_vertex = vertex[idx];
-                        vertex.push(new_vertex);
+        // vertices without faces (or with degenerate ones only) keep their normal
+        let old = self.normals.get(frame);
+        (0..positions.len())
+            .map(|i| match normalize(sums[group[i]]) {
+                Some(normal) => normal,
+                None => old.and_then(|n| n.get(i)).copied().unwrap_or([0.0; 3]),
+            })
+            .collect()
+    }
+
+    /// computes a mikktspace tangent for every vertex of every frame, the
+    /// tangents blender and most engines bake and shade normal maps with.
+    /// they are computed for the counter clockwise winding the exporters
+    /// write and with t flipped, so the bitangent, w times
+    /// cross(normal, tangent), points up in the skin as gltf expects.
+    /// mikktspace gives every corner of a face its own tangent. where the
+    /// corners of a vertex get different ones, like where mirrored
+    /// texcoords meet, the vertex is duplicated so that every face keeps
+    /// its tangents.
+    /// call it again after the normals have changed
+    pub fn generate_tangents(&mut self) {
+        let frames = self.vertices.len();
+        let mut corners = Vec::with_capacity(frames);
+        for frame in 0..frames {
+            corners.push(self.corner_tangents(frame));
+        }
+
+        // the versions of every vertex, told apart by the
+        // tangents of their corners in all frames
+        let mut versions = HashMap::<usize, Vec<(Vec<u32>, usize)>>::new();
+        for f in 0..self.indices.len() {
+            let (a, b, c) = self.indices[f];
+            let mut face = [a, b, c];
+            for i in 0..3 {
+                let key: Vec<u32> = corners
+                    .iter()
+                    .flat_map(|frame| frame[f * 3 + i].iter().map(|x| x.to_bits()))
+                    .collect();
+                let list = versions.entry(face[i]).or_default();
+                match list.iter().find(|(k, _)| *k == key) {
+                    Some(&(_, v)) => face[i] = v,
+                    None => {
+                        if !list.is_empty() {
+                            face[i] = self.duplicate(face[i]);
+                        }
+                        list.push((key, face[i]));
                     }
-                    let new_idx = vertices[0].len() - 1;
-                    indices.push(new_idx);
-                    texcoords[new_idx] = (s, t);
-                } else {
-                    let s = (model.texcoords[idx].s as f32 + 0.5) / w;
-                    let t = (model.texcoords[idx].t as f32 + 0.5) / h;
-                    texcoords[idx] = (s, t);
-                    indices.push(idx);
                 }
             }
+            self.indices[f] = (face[0], face[1], face[2]);
         }
 
-        let mut fi = Vec::new();
-        for i in 0..indices.len() / 3 {
-            let a = indices[i * 3 + 0];
-            let b = indices[i * 3 + 1];
-            let c = indices[i * 3 + 2];
-            fi.push((a, b, c));
+        let mut tangents = Vec::with_capacity(frames);
+        for frame in 0..frames {
+            // vertices without faces get any tangent
+            let mut frame_tangents: Vec<[f32; 4]> = self
+                .frame_normals(frame)
+                .iter()
+                .map(|&n| {
+                    let t = perpendicular(n);
+                    [t[0], t[1], t[2], 1.0]
+                })
+                .collect();
+            for f in 0..self.indices.len() {
+                let (a, b, c) = self.indices[f];
+                frame_tangents[a] = corners[frame][f * 3 + 0];
+                frame_tangents[b] = corners[frame][f * 3 + 1];
+                frame_tangents[c] = corners[frame][f * 3 + 2];
+            }
+            tangents.push(frame_tangents);
         }
+        self.tangents = tangents;
+    }
 
-        texcoords.truncate(vertices[0].len());
-        FlatModel {
-            vertices: vertices,
-            texcoords: texcoords,
-            indices: fi,
+    /// the mikktspace tangents of the corners of every face, in
+    /// the order of the corners in indices
+    fn corner_tangents(&self, frame: usize) -> Vec<[f32; 4]> {
+        let normals = self.frame_normals(frame);
+        // wound counter clockwise, v runs up the skin while t runs down
+        let mut triangles = Vec::with_capacity(self.indices.len());
+        for &(a, b, c) in &self.indices {
+            triangles.push([a, c, b]);
+        }
+        let mut texcoords = Vec::with_capacity(self.texcoords.len());
+        for &(s, t) in &self.texcoords {
+            texcoords.push([s, -t]);
+        }
+
+        let mut corners =
+            mikktspace::generate(&self.vertices[frame], &normals, &texcoords, &triangles);
+        for f in 0..triangles.len() {
+            corners.swap(f * 3 + 1, f * 3 + 2);
         }
+        corners
     }
+
+    /// the normals of a frame, smooth ones if it has none
+    fn frame_normals(&self, frame: usize) -> Vec<vec3_t> {
+        match self.normals.get(frame) {
+            Some(normals) if normals.len() == self.vertices[frame].len() => normals.clone(),
+            _ => self.smooth_normals(frame, NormalWeighting::Angle),
+        }
+    }
+
+    /// appends a copy of vertex v to every frame and returns its index
+    fn duplicate(&mut self, v: usize) -> usize {
+        for frame in &mut self.vertices {
+            let vertex = frame[v];
+            frame.push(vertex);
+        }
+        for frame in &mut self.normals {
+            if let Some(&normal) = frame.get(v) {
+                frame.push(normal);
+            }
+        }
+        let st = self.texcoords[v];
+        self.texcoords.push(st);
+        self.texcoords.len() - 1
+    }
+
+    /// positions and normals blended between frame a and b.
+    /// blend 0 gives a, 1 gives b
+    pub fn pose(&self, a: usize, b: usize, blend: f32) -> Pose {
+        let normals = |frame: usize| self.normals.get(frame).map_or(&[][..], |n| &n[..]);
+        animation::lerp(
+            &self.vertices[a],
+            normals(a),
+            &self.vertices[b],
+            normals(b),
+            blend,
+        )
+    }
+
+    /// pose at time seconds into clip. see Clip::frames_at
+    pub fn sample(&self, clip: &Clip, time: f32) -> Pose {
+        let (a, b, blend) = clip.frames_at(time);
+        self.pose(a, b, blend)
+    }
+
+    pub fn from_md2(model: &super::md2::Model) -> Self {
+        Self::from_model(model)
+    }
+
+    /// back faces of vertices on the seam use the
+    /// back half of the skin and get their own vertex
+    pub fn from_mdl(model: &super::mdl::Model) -> Self {
+        Self::from_model(model)
+    }
+}
+
+/// any unit vector perpendicular to n, for vertices without texture space
+fn perpendicular(n: vec3_t) -> vec3_t {
+    let axis = if n[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    normalize(cross(n, axis)).unwrap_or(axis)
 }
@@ -0,0 +1,437 @@
+use super::animation::Clip;
+use super::flat_model::FlatModel;
+use super::image::Image;
+use super::vec3_t;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const NEAREST: u32 = 9728;
+
+// rotates quake's z up to gltf's y up (-90 degrees around x)
+const Z_UP_TO_Y_UP: [f32; 4] = [-0.707_106_77, 0.0, 0.0, 0.707_106_77];
+
+const GLB_MAGIC: u32 = 0x4654_6c67; // glTF
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+
+/// collects buffer views and accessors while
+/// the binary buffer is filled
+struct Builder {
+    bin: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl Builder {
+    fn view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let target = match target {
+            Some(target) => format!(", \"target\": {}", target),
+            None => String::new(),
+        };
+        self.views.push(format!(
+            "{{\"buffer\": 0, \"byteOffset\": {}, \"byteLength\": {}{}}}",
+            self.bin.len(),
+            data.len(),
+            target
+        ));
+        self.bin.extend_from_slice(data);
+        self.views.len() - 1
+    }
+
+    fn accessor(&mut self, view: usize, component: u32, count: usize, typ: &str) -> usize {
+        self.accessors.push(format!(
+            "{{\"bufferView\": {}, \"componentType\": {}, \"count\": {}, \"type\": \"{}\"}}",
+            view, component, count, typ
+        ));
+        self.accessors.len() - 1
+    }
+
+    fn accessor_bounds(
+        &mut self,
+        view: usize,
+        count: usize,
+        typ: &str,
+        min: &str,
+        max: &str,
+    ) -> usize {
+        self.accessors.push(format!(
+            "{{\"bufferView\": {}, \"componentType\": {}, \"count\": {}, \"type\": \"{}\", \"min\": [{}], \"max\": [{}]}}",
+            view, FLOAT, count, typ, min, max
+        ));
+        self.accessors.len() - 1
+    }
+
+    fn floats(&mut self, values: &[f32], target: Option<u32>) -> usize {
+        let mut data = Vec::with_capacity(values.len() * 4);
+        for v in values {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        self.view(&data, target)
+    }
+
+    fn vec4s(&mut self, values: &[[f32; 4]]) -> usize {
+        let flat: Vec<f32> = values.iter().flat_map(|v| v.iter().copied()).collect();
+        let view = self.floats(&flat, Some(ARRAY_BUFFER));
+        self.accessor(view, FLOAT, values.len(), "VEC4")
+    }
+
+    fn vec3s(&mut self, values: &[vec3_t], bounds: bool) -> usize {
+        let flat: Vec<f32> = values.iter().flat_map(|v| v.iter().copied()).collect();
+        let view = self.floats(&flat, Some(ARRAY_BUFFER));
+        if !bounds {
+            return self.accessor(view, FLOAT, values.len(), "VEC3");
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for v in values {
+            for i in 0..3 {
+                min[i] = min[i].min(v[i]);
+                max[i] = max[i].max(v[i]);
+            }
+        }
+        let min = format!("{}, {}, {}", min[0], min[1], min[2]);
+        let max = format!("{}, {}, {}", max[0], max[1], max[2]);
+        self.accessor_bounds(view, values.len(), "VEC3", &min, &max)
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn join(items: &[String]) -> String {
+    items.join(",\n\t\t")
+}
+
+fn invalid(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+/// every frame needs as many vertices as there are texcoords.
+/// normals and tangents may be empty, otherwise they need
+/// the same layout as the vertices
+fn check(model: &FlatModel) -> Result<(), std::io::Error> {
+    if model.vertices.is_empty() {
+        return Err(invalid("model has no frames"));
+    }
+    let len = model.texcoords.len();
+    if model.vertices.iter().any(|frame| frame.len() != len) {
+        return Err(invalid("frames and texcoords differ in length"));
+    }
+    if model
+        .indices
+        .iter()
+        .any(|(a, b, c)| *a.max(b).max(c) >= len)
+    {
+        return Err(invalid("index out of range"));
+    }
+
+    let frames = model.vertices.len();
+    if !model.normals.is_empty()
+        && (model.normals.len() != frames || model.normals.iter().any(|n| n.len() != len))
+    {
+        return Err(invalid("normals do not match the frames"));
+    }
+    if !model.tangents.is_empty()
+        && (model.tangents.len() != frames || model.tangents.iter().any(|t| t.len() != len))
+    {
+        return Err(invalid("tangents do not match the frames"));
+    }
+    Ok(())
+}
+
+/// builds the gltf json and the binary buffer. the first frame is
+/// the base mesh, every other frame becomes a morph target and each
+/// clip an animation stepping the target weights.
+/// clips outside of the frame range or without fps are skipped.
+/// normals and tangents are written if the model has them.
+/// triangles are wound counter clockwise and the node turns
+/// the model from z up to y up
+fn build(
+    model: &FlatModel,
+    clips: &[Clip],
+    skin: Option<&Image>,
+    bin_uri: Option<&str>,
+) -> Result<(Vec<u8>, Vec<u8>), std::io::Error> {
+    check(model)?;
+
+    let mut builder = Builder {
+        bin: Vec::new(),
+        views: Vec::new(),
+        accessors: Vec::new(),
+    };
+
+    // indices. quake winds clockwise, gltf counter clockwise
+    let mut data = Vec::with_capacity(model.indices.len() * 12);
+    for (a, b, c) in &model.indices {
+        data.extend_from_slice(&(*a as u32).to_le_bytes());
+        data.extend_from_slice(&(*c as u32).to_le_bytes());
+        data.extend_from_slice(&(*b as u32).to_le_bytes());
+    }
+    let view = builder.view(&data, Some(ELEMENT_ARRAY_BUFFER));
+    let indices = builder.accessor(view, UNSIGNED_INT, model.indices.len() * 3, "SCALAR");
+
+    // base mesh
+    let base = &model.vertices[0];
+    let has_normals = !model.normals.is_empty();
+    let position = builder.vec3s(base, true);
+    let normal = if has_normals {
+        format!(", \"NORMAL\": {}", builder.vec3s(&model.normals[0], false))
+    } else {
+        String::new()
+    };
+    let flat: Vec<f32> = model
+        .texcoords
+        .iter()
+        .flat_map(|(s, t)| vec![*s, *t])
+        .collect();
+    let view = builder.floats(&flat, Some(ARRAY_BUFFER));
+    let texcoord = builder.accessor(view, FLOAT, model.texcoords.len(), "VEC2");
+    let has_tangents = !model.tangents.is_empty();
+    let tangent = if has_tangents {
+        format!(", \"TANGENT\": {}", builder.vec4s(&model.tangents[0]))
+    } else {
+        String::new()
+    };
+
+    // morph targets hold the difference to the base mesh
+    let mut targets = Vec::<String>::new();
+    for (idx, frame) in model.vertices.iter().enumerate().skip(1) {
+        let offsets: Vec<vec3_t> = frame
+            .iter()
+            .zip(base)
+            .map(|(v, b)| [v[0] - b[0], v[1] - b[1], v[2] - b[2]])
+            .collect();
+        let position = builder.vec3s(&offsets, true);
+        let normal = if has_normals {
+            let normal_offsets: Vec<vec3_t> = model.normals[idx]
+                .iter()
+                .zip(&model.normals[0])
+                .map(|(n, b)| [n[0] - b[0], n[1] - b[1], n[2] - b[2]])
+                .collect();
+            format!(", \"NORMAL\": {}", builder.vec3s(&normal_offsets, false))
+        } else {
+            String::new()
+        };
+        // tangent targets are vec3, w stays that of the base mesh
+        let tangent = if has_tangents {
+            let tangent_offsets: Vec<vec3_t> = model.tangents[idx]
+                .iter()
+                .zip(&model.tangents[0])
+                .map(|(t, b)| [t[0] - b[0], t[1] - b[1], t[2] - b[2]])
+                .collect();
+            format!(", \"TANGENT\": {}", builder.vec3s(&tangent_offsets, false))
+        } else {
+            String::new()
+        };
+        targets.push(format!(
+            "{{\"POSITION\": {}{}{}}}",
+            position, normal, tangent
+        ));
+    }
+    let num_targets = targets.len();
+
+    // one animation per clip. frame 0 is the base mesh,
+    // frame n is morph target n - 1
+    let mut animations = Vec::<String>::new();
+    if num_targets > 0 {
+        for clip in clips {
+            if clip.first > clip.last || clip.last >= model.vertices.len() || clip.fps <= 0.0 {
+                continue;
+            }
+            let times: Vec<f32> = (0..clip.num_frames())
+                .map(|k| k as f32 / clip.fps)
+                .collect();
+            let mut weights = vec![0f32; clip.num_frames() * num_targets];
+            for (k, frame) in (clip.first..=clip.last).enumerate() {
+                if frame > 0 {
+                    weights[k * num_targets + frame - 1] = 1.0;
+                }
+            }
+
+            let view = builder.floats(&times, None);
+            let min = format!("{}", times[0]);
+            let max = format!("{}", times[times.len() - 1]);
+            let input = builder.accessor_bounds(view, times.len(), "SCALAR", &min, &max);
+            let view = builder.floats(&weights, None);
+            let output = builder.accessor(view, FLOAT, weights.len(), "SCALAR");
+
+            animations.push(format!(
+                "{{\"name\": \"{}\", \"samplers\": [{{\"input\": {}, \"output\": {}, \"interpolation\": \"STEP\"}}], \"channels\": [{{\"sampler\": 0, \"target\": {{\"node\": 0, \"path\": \"weights\"}}}}]}}",
+                escape(&clip.name), input, output
+            ));
+        }
+    }
+
+    // skin
+    let image_view = match skin {
+        Some(skin) => {
+            let mut png = Vec::new();
+            skin.write_png(&mut png)?;
+            Some(builder.view(&png, None))
+        }
+        None => None,
+    };
+
+    let mut json = Vec::<u8>::new();
+    write!(
+        json,
+        "{{\n\t\"asset\": {{\"version\": \"2.0\", \"generator\": \"model-reader\"}},\n"
+    )?;
+    write!(
+        json,
+        "\t\"scene\": 0,\n\t\"scenes\": [{{\"nodes\": [0]}}],\n"
+    )?;
+    write!(
+        json,
+        "\t\"nodes\": [{{\"mesh\": 0, \"rotation\": [{}, {}, {}, {}]}}],\n",
+        Z_UP_TO_Y_UP[0], Z_UP_TO_Y_UP[1], Z_UP_TO_Y_UP[2], Z_UP_TO_Y_UP[3]
+    )?;
+
+    let material = if image_view.is_some() {
+        ", \"material\": 0"
+    } else {
+        ""
+    };
+    let targets = if num_targets > 0 {
+        format!(
+            ", \"targets\": [\n\t\t\t{}\n\t\t]",
+            targets.join(",\n\t\t\t")
+        )
+    } else {
+        String::new()
+    };
+    let weights = if num_targets > 0 {
+        format!(", \"weights\": [{}]", vec!["0"; num_targets].join(", "))
+    } else {
+        String::new()
+    };
+    write!(
+        json,
+        "\t\"meshes\": [{{\"primitives\": [{{\"attributes\": {{\"POSITION\": {}{}, \"TEXCOORD_0\": {}{}}}, \"indices\": {}, \"mode\": 4{}{}}}]{}}}],\n",
+        position, normal, texcoord, tangent, indices, material, targets, weights
+    )?;
+
+    if let Some(image_view) = image_view {
+        write!(json, "\t\"materials\": [{{\"pbrMetallicRoughness\": {{\"baseColorTexture\": {{\"index\": 0}}, \"metallicFactor\": 0, \"roughnessFactor\": 1}}}}],\n")?;
+        write!(
+            json,
+            "\t\"textures\": [{{\"sampler\": 0, \"source\": 0}}],\n"
+        )?;
+        write!(
+            json,
+            "\t\"samplers\": [{{\"magFilter\": {}, \"minFilter\": {}}}],\n",
+            NEAREST, NEAREST
+        )?;
+        write!(
+            json,
+            "\t\"images\": [{{\"bufferView\": {}, \"mimeType\": \"image/png\"}}],\n",
+            image_view
+        )?;
+    }
+
+    if !animations.is_empty() {
+        write!(
+            json,
+            "\t\"animations\": [\n\t\t{}\n\t],\n",
+            join(&animations)
+        )?;
+    }
+
+    write!(
+        json,
+        "\t\"accessors\": [\n\t\t{}\n\t],\n",
+        join(&builder.accessors)
+    )?;
+    write!(
+        json,
+        "\t\"bufferViews\": [\n\t\t{}\n\t],\n",
+        join(&builder.views)
+    )?;
+
+    while builder.bin.len() % 4 != 0 {
+        builder.bin.push(0);
+    }
+    let uri = match bin_uri {
+        Some(uri) => format!(", \"uri\": \"{}\"", escape(uri)),
+        None => String::new(),
+    };
+    write!(
+        json,
+        "\t\"buffers\": [{{\"byteLength\": {}{}}}]\n}}\n",
+        builder.bin.len(),
+        uri
+    )?;
+
+    Ok((json, builder.bin))
+}
+
+/// writes name.gltf and name.bin to dir. see write_glb
+pub fn write_gltf(
+    model: &FlatModel,
+    clips: &[Clip],
+    skin: Option<&Image>,
+    dir: &Path,
+    name: &str,
+) -> Result<(), std::io::Error> {
+    let bin_name = format!("{}.bin", name);
+    let (json, bin) = build(model, clips, skin, Some(&bin_name))?;
+
+    let mut writer = BufWriter::new(File::create(dir.join(format!("{}.gltf", name)))?);
+    writer.write_all(&json)?;
+    writer.flush()?;
+
+    let mut writer = BufWriter::new(File::create(dir.join(&bin_name))?);
+    writer.write_all(&bin)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// writes the model as binary gltf. the first frame is the base mesh,
+/// the other frames are morph targets. each clip becomes an animation
+/// that steps the morph target weights at clip.fps.
+/// the skin is embedded as png texture
+pub fn write_glb(
+    model: &FlatModel,
+    clips: &[Clip],
+    skin: Option<&Image>,
+    writer: &mut dyn Write,
+) -> Result<(), std::io::Error> {
+    let (mut json, bin) = build(model, clips, skin, None)?;
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+
+    writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&bin)?;
+    Ok(())
+}
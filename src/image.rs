@@ -0,0 +1,145 @@
+use super::palette::Palette;
+use std::io::Write;
+
+/// palette indices from this one up to 255 are
+/// drawn fullbright (not affected by lighting)
+pub const FULLBRIGHT_START: u8 = 224;
+
+/// rgba8 bitmap. 4 bytes per pixel, row by row
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// converts palette indices to rgba.
+    /// if split_fullbright is set, the fullbright pixels are moved
+    /// to a second image (emissive mask): they are black in the
+    /// color image and every other pixel of the mask is transparent.
+    /// if indices is shorter than width * height the missing
+    /// pixels are transparent black
+    pub fn from_indexed(
+        indices: &[u8],
+        width: u32,
+        height: u32,
+        palette: &Palette,
+        split_fullbright: bool,
+    ) -> (Image, Option<Image>) {
+        let len = width as usize * height as usize;
+        let mut color = Vec::<u8>::with_capacity(len * 4);
+        let mut emissive = if split_fullbright {
+            Some(Vec::<u8>::with_capacity(len * 4))
+        } else {
+            None
+        };
+
+        for idx in indices.iter().take(len) {
+            let rgb = palette.color(*idx);
+            match emissive {
+                Some(ref mut mask) if *idx >= FULLBRIGHT_START => {
+                    color.extend_from_slice(&[0, 0, 0, 255]);
+                    mask.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+                }
+                Some(ref mut mask) => {
+                    color.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+                    mask.extend_from_slice(&[0, 0, 0, 0]);
+                }
+                None => color.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]),
+            }
+        }
+        color.resize(len * 4, 0);
+        if let Some(ref mut mask) = emissive {
+            mask.resize(len * 4, 0);
+        }
+
+        let color = Image {
+            width: width,
+            height: height,
+            data: color,
+        };
+        let emissive = emissive.map(|data| Image {
+            width: width,
+            height: height,
+            data: data,
+        });
+        (color, emissive)
+    }
+}
+
+impl Image {
+    /// writes the image as png. the pixel data is stored
+    /// uncompressed, which every png reader accepts
+    pub fn write_png(&self, writer: &mut dyn Write) -> std::result::Result<(), std::io::Error> {
+        writer.write_all(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a])?;
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]); // 8 bit rgba, no interlace
+        write_chunk(writer, b"IHDR", &ihdr)?;
+
+        // every scanline starts with filter type 0 (none)
+        let stride = self.width as usize * 4;
+        let mut raw = Vec::with_capacity((stride + 1) * self.height as usize);
+        for row in self.data.chunks(stride.max(1)).take(self.height as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        // zlib stream of stored deflate blocks
+        let mut idat = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(0xffff).peekable();
+        if blocks.peek().is_none() {
+            idat.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let last = if blocks.peek().is_none() { 1 } else { 0 };
+            let len = block.len() as u16;
+            idat.push(last);
+            idat.extend_from_slice(&len.to_le_bytes());
+            idat.extend_from_slice(&(!len).to_le_bytes());
+            idat.extend_from_slice(block);
+        }
+        idat.extend_from_slice(&adler32(&raw).to_be_bytes());
+        write_chunk(writer, b"IDAT", &idat)?;
+
+        write_chunk(writer, b"IEND", &[])
+    }
+}
+
+fn write_chunk(
+    writer: &mut dyn Write,
+    typ: &[u8; 4],
+    data: &[u8],
+) -> std::result::Result<(), std::io::Error> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(typ)?;
+    writer.write_all(data)?;
+    let crc = crc32(crc32(0xffff_ffff, typ), data) ^ 0xffff_ffff;
+    writer.write_all(&crc.to_be_bytes())
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -1,16 +1,293 @@
+#![allow(
+    clippy::identity_op,
+    clippy::needless_range_loop,
+    clippy::redundant_field_names,
+    clippy::write_with_newline
+)]
+
 pub mod mdl;
 pub mod md2;
+pub mod md3;
 pub mod flat_model;
+pub mod animation;
+pub mod gltf;
+pub mod image;
+pub mod obj;
+pub mod palette;
+pub mod pcx;
+pub mod spr;
+pub mod validation;
+
+mod mikktspace;
 
 #[allow(non_camel_case_types)]
 type vec3_t = [f32; 3];
 
+extern crate byteorder;
+use animation::{Clip, Pose};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Read, Seek, SeekFrom};
+use validation::Issue;
+
 pub const NORMALS: [[f32; 3]; 162] = include!("normals.txt");
 pub const COLORMAP: [[u8; 3]; 256] = include!("colormap.txt");
 
+/// a triangle of an AliasModel. vertex indexes the positions and
+/// normals of a frame, texcoord indexes AliasModel::texcoords
+pub struct Face {
+    pub vertex: [usize; 3],
+    pub texcoord: [usize; 3],
+}
+
+/// a skin as it is stored in the model file
+pub enum SkinRef<'a> {
+    /// width * height palette indices
+    Indexed {
+        width: u32,
+        height: u32,
+        data: &'a [u8],
+    },
+    /// path of an image or shader, relative to the game directory
+    Path(&'a str),
+}
+
+/// common view on the vertex animated model formats.
+/// mdl group frames are expanded, so every frame index
+/// addresses a single pose
+pub trait AliasModel {
+    fn num_frames(&self) -> usize;
+    fn frame_name(&self, frame: usize) -> &str;
+
+    /// number of positions (and normals) in each frame
+    fn num_vertices(&self) -> usize;
+
+    /// uncompressed positions of frame
+    fn positions(&self, frame: usize) -> Vec<vec3_t>;
+
+    /// normals of frame, resolved from the compressed indices
+    fn normals(&self, frame: usize) -> Vec<vec3_t>;
+
+    fn faces(&self) -> Vec<Face>;
+
+    /// texcoords scaled to 0..1, origin is the top left corner
+    fn texcoords(&self) -> Vec<(f32, f32)>;
+
+    fn skins(&self) -> Vec<SkinRef<'_>>;
+
+    fn frame_names(&self) -> Vec<&str> {
+        (0..self.num_frames()).map(|i| self.frame_name(i)).collect()
+    }
+
+    /// positions and normals blended between frame a and b.
+    /// blend 0 gives a, 1 gives b
+    fn pose(&self, a: usize, b: usize, blend: f32) -> Pose {
+        animation::lerp(
+            &self.positions(a),
+            &self.normals(a),
+            &self.positions(b),
+            &self.normals(b),
+            blend,
+        )
+    }
+
+    /// pose at time seconds into clip. see Clip::frames_at
+    fn sample(&self, clip: &Clip, time: f32) -> Pose {
+        let (a, b, blend) = clip.frames_at(time);
+        self.pose(a, b, blend)
+    }
+}
+
+/// a model of any of the supported formats
+pub enum AnyModel {
+    Mdl(mdl::Model),
+    Md2(md2::Model),
+    Md3(md3::Model),
+}
+
+impl AnyModel {
+    pub fn as_alias_model(&self) -> &dyn AliasModel {
+        match self {
+            AnyModel::Mdl(model) => model,
+            AnyModel::Md2(model) => model,
+            AnyModel::Md3(model) => model,
+        }
+    }
+}
+
+impl AliasModel for AnyModel {
+    fn num_frames(&self) -> usize {
+        self.as_alias_model().num_frames()
+    }
+
+    fn frame_name(&self, frame: usize) -> &str {
+        self.as_alias_model().frame_name(frame)
+    }
+
+    fn num_vertices(&self) -> usize {
+        self.as_alias_model().num_vertices()
+    }
+
+    fn positions(&self, frame: usize) -> Vec<vec3_t> {
+        self.as_alias_model().positions(frame)
+    }
+
+    fn normals(&self, frame: usize) -> Vec<vec3_t> {
+        self.as_alias_model().normals(frame)
+    }
+
+    fn faces(&self) -> Vec<Face> {
+        self.as_alias_model().faces()
+    }
+
+    fn texcoords(&self) -> Vec<(f32, f32)> {
+        self.as_alias_model().texcoords()
+    }
+
+    fn skins(&self) -> Vec<SkinRef<'_>> {
+        self.as_alias_model().skins()
+    }
+}
+
+/// upper bounds for what a reader may allocate. counts from
+/// the file are checked against them before allocating, so a
+/// hostile header fails with ErrorKind::LimitExceeded
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// width * height of one skin image
+    pub max_skin_pixels: u64,
+    /// frames of the model, subframes of mdl groups included
+    pub max_frames: u64,
+    /// sum of the sizes of everything the reader allocates
+    pub max_total_bytes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_skin_pixels: 4096 * 4096,
+            max_frames: 16384,
+            max_total_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// options for the from_reader_with functions
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// validate the model after reading and fail with
+    /// ErrorKind::Invalid if there are any issues
+    pub strict: bool,
+    pub limits: Limits,
+}
+
+/// peeks the ident of the model at the current position
+/// and loads it with the matching reader
+pub fn load_any<R: Read + Seek>(reader: &mut R) -> Result<AnyModel> {
+    load_any_with(reader, &LoadOptions::default())
+}
+
+pub fn load_any_with<R: Read + Seek>(reader: &mut R, options: &LoadOptions) -> Result<AnyModel> {
+    let start = reader
+        .stream_position()
+        .map_err(|e| Error::io(e, "failed to get stream position."))?;
+    let ident = reader
+        .read_i32::<LittleEndian>()
+        .map_err(|e| Error::io(e, "failed to read 'ident'.").at(Section::Header, start))?;
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(|e| Error::io(e, "failed to seek back to start."))?;
+
+    match ident {
+        mdl::HEADER_IDENT => Ok(AnyModel::Mdl(mdl::Model::from_reader_with(
+            reader, options,
+        )?)),
+        md2::HEADER_IDENT => Ok(AnyModel::Md2(md2::Model::from_reader_with(
+            reader, options,
+        )?)),
+        md3::HEADER_IDENT => Ok(AnyModel::Md3(md3::Model::from_reader_with(
+            reader, options,
+        )?)),
+        _ => Err(Error::unknown_format(ident)),
+    }
+}
+
+/// the part of a file that was being read when an error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Skins,
+    Texcoords,
+    Triangles,
+    Frames,
+    /// md2 gl commands
+    Commands,
+    /// md3 tags
+    Tags,
+    /// md3 surfaces, including their headers, shaders and vertices
+    Surfaces,
+    /// pcx image data
+    Pixels,
+    Palette,
+}
+
+impl std::fmt::Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Section::Header => "header",
+            Section::Skins => "skins",
+            Section::Texcoords => "texcoords",
+            Section::Triangles => "triangles",
+            Section::Frames => "frames",
+            Section::Commands => "commands",
+            Section::Tags => "tags",
+            Section::Surfaces => "surfaces",
+            Section::Pixels => "pixels",
+            Section::Palette => "palette",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    BadIdent {
+        found: i32,
+        expected: i32,
+    },
+    BadVersion {
+        found: i32,
+        expected: i32,
+    },
+    /// load_any found none of the known idents
+    UnknownFormat {
+        magic: [u8; 4],
+    },
+    /// the file ended in the middle of a read
+    Truncated,
+    /// any other io error. Error::source gives the cause
+    Io,
+    Utf8,
+    Unsupported,
+    InvalidIndex {
+        index: usize,
+        len: usize,
+    },
+    LimitExceeded {
+        value: u64,
+        limit: u64,
+    },
+    /// strict loading found issues in the model
+    Invalid {
+        issues: Vec<Issue>,
+    },
+}
+
 #[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     desc: String,
+    section: Option<Section>,
+    offset: Option<u64>,
     source: Option<std::io::Error>,
 }
 
@@ -18,50 +295,331 @@ type Result<T> = std::result::Result<T, Error>;
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "{}", self.desc)
+        write!(f, "{}", self.desc)?;
+        match (self.section, self.offset) {
+            (Some(section), Some(offset)) => {
+                write!(f, " (section: {}, offset: {})", section, offset)
+            }
+            (Some(section), None) => write!(f, " (section: {})", section),
+            (None, Some(offset)) => write!(f, " (offset: {})", offset),
+            (None, None) => Ok(()),
+        }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
 
 impl Error {
-    fn ident(actual: i32, expected: i32) -> Self {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// the section that was being read, if the error came from a reader
+    pub fn section(&self) -> Option<Section> {
+        self.section
+    }
+
+    /// byte offset of the read that failed. for seekable readers
+    /// this is the stream position, otherwise it is counted from
+    /// where reading started
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    fn new(kind: ErrorKind, desc: String) -> Self {
         Error {
-            desc: format!(
-                "unexpectd ident value. expected: {}. actual: {}",
-                expected,
-                actual
-            ),
+            kind: kind,
+            desc: desc,
+            section: None,
+            offset: None,
             source: None,
         }
     }
 
+    /// sets where the error happened. called by the from_reader
+    /// functions, so the outermost reader decides
+    fn at(mut self, section: Section, offset: u64) -> Self {
+        self.section = Some(section);
+        self.offset = Some(offset);
+        self
+    }
+
+    fn ident(actual: i32, expected: i32) -> Self {
+        Error::new(
+            ErrorKind::BadIdent {
+                found: actual,
+                expected: expected,
+            },
+            format!(
+                "unexpectd ident value. expected: {}. actual: {}",
+                expected, actual
+            ),
+        )
+    }
+
     fn version(actual: i32, expected: i32) -> Self {
-        Error {
-            desc: format!("unexpected version value. expected: {}. actual: {}", expected, actual),
-            source: None,
-        }
+        Error::new(
+            ErrorKind::BadVersion {
+                found: actual,
+                expected: expected,
+            },
+            format!(
+                "unexpected version value. expected: {}. actual: {}",
+                expected, actual
+            ),
+        )
     }
 
     fn io(src: std::io::Error, msg: &str) -> Self {
-        Error {
-            desc: format!("io error: {}. message: {}", &src, msg),
-            source: Some(src),
-        }
+        let kind = if src.kind() == std::io::ErrorKind::UnexpectedEof {
+            ErrorKind::Truncated
+        } else {
+            ErrorKind::Io
+        };
+        let mut error = Error::new(kind, format!("io error: {}. message: {}", &src, msg));
+        error.source = Some(src);
+        error
     }
 
     fn utf8(src: std::str::Utf8Error, msg: &str) -> Self {
-        Error {
-            desc: format!("utf8 error: {}. message: {}", src, msg),
-            source: None,
+        Error::new(
+            ErrorKind::Utf8,
+            format!("utf8 error: {}. message: {}", src, msg),
+        )
+    }
+
+    fn unknown_format(ident: i32) -> Self {
+        let magic = ident.to_le_bytes();
+        let printable: String = magic
+            .iter()
+            .map(|b| {
+                std::ascii::escape_default(*b)
+                    .map(|c| c as char)
+                    .collect::<String>()
+            })
+            .collect();
+        Error::new(
+            ErrorKind::UnknownFormat { magic: magic },
+            format!(
+                "unknown model format. magic: '{}' ({:#010x}). expected one of: IDPO (mdl), IDP2 (md2), IDP3 (md3)",
+                printable, ident as u32
+            ),
+        )
+    }
+
+    /// Ok if there are no issues
+    fn check(issues: Vec<Issue>) -> Result<()> {
+        if issues.is_empty() {
+            return Ok(());
         }
+
+        let desc = format!(
+            "model failed validation. {} issue(s). first: {}",
+            issues.len(),
+            issues[0]
+        );
+        Err(Error::new(ErrorKind::Invalid { issues: issues }, desc))
+    }
+
+    fn limit(what: &str, value: u64, limit: u64) -> Self {
+        Error::new(
+            ErrorKind::LimitExceeded {
+                value: value,
+                limit: limit,
+            },
+            format!(
+                "{} exceeds the limit. value: {}. limit: {}",
+                what, value, limit
+            ),
+        )
     }
 
     fn unsupported(msg: &str) -> Self {
-        Error {
-            desc: format!("{}", msg),
-            source: None,
+        Error::new(ErrorKind::Unsupported, msg.to_string())
+    }
+
+    fn index(idx: usize, len: usize) -> Self {
+        Error::new(
+            ErrorKind::InvalidIndex {
+                index: idx,
+                len: len,
+            },
+            format!("index out of range. index: {}. len: {}", idx, len),
+        )
+    }
+}
+
+/// keeps track of what a reader allocated, checked against Limits
+struct Budget<'a> {
+    limits: &'a Limits,
+    frames: u64,
+    bytes: u64,
+}
+
+impl<'a> Budget<'a> {
+    fn new(limits: &'a Limits) -> Self {
+        Budget {
+            limits: limits,
+            frames: 0,
+            bytes: 0,
+        }
+    }
+
+    /// checks that count elements of size bytes fit into the
+    /// limits and returns count, ready to be used as capacity.
+    /// what names the count in errors
+    fn alloc(&mut self, what: &str, count: i64, size: usize) -> Result<usize> {
+        if count < 0 {
+            return Err(Error::unsupported(&format!(
+                "negative '{}': {}.",
+                what, count
+            )));
+        }
+
+        let bytes = (count as u64).saturating_mul(size as u64);
+        let total = self.bytes.saturating_add(bytes);
+        if total > self.limits.max_total_bytes {
+            return Err(Error::limit(what, total, self.limits.max_total_bytes));
+        }
+        self.bytes = total;
+        Ok(count as usize)
+    }
+
+    /// like alloc for count images of width * height bytes.
+    /// returns the number of bytes
+    fn skin(&mut self, count: i64, width: i32, height: i32) -> Result<usize> {
+        if width < 0 || height < 0 {
+            return Err(Error::unsupported(&format!(
+                "negative skin size: {}x{}.",
+                width, height
+            )));
         }
+
+        let pixels = width as u64 * height as u64;
+        if pixels > self.limits.max_skin_pixels {
+            return Err(Error::limit(
+                "skin size",
+                pixels,
+                self.limits.max_skin_pixels,
+            ));
+        }
+        let count = self.alloc("skin data", count, pixels as usize)?;
+        Ok(count * pixels as usize)
+    }
+
+    /// counts count frames of size bytes each
+    fn frames(&mut self, count: i64, size: usize) -> Result<usize> {
+        let frames = self.frames.saturating_add(count.max(0) as u64);
+        if frames > self.limits.max_frames {
+            return Err(Error::limit("frames", frames, self.limits.max_frames));
+        }
+        let count = self.alloc("frames", count, size)?;
+        self.frames = frames;
+        Ok(count)
+    }
+}
+
+/// counts the bytes read, so the readers can tell the
+/// offset of the read that failed. seeking sets the
+/// position to the one reported by the inner reader
+struct Tracker<R> {
+    inner: R,
+    pos: u64,
+    /// position at the start of the last read
+    last: u64,
+}
+
+impl<R> Tracker<R> {
+    fn new(inner: R) -> Self {
+        Tracker {
+            inner: inner,
+            pos: 0,
+            last: 0,
+        }
+    }
+}
+
+impl<R: Seek> Tracker<R> {
+    /// offsets of seekable readers are stream positions
+    fn seekable(inner: R) -> Result<Self> {
+        let mut tracker = Tracker::new(inner);
+        tracker
+            .stream_position()
+            .map_err(|e| Error::io(e, "failed to get stream position."))?;
+        Ok(tracker)
+    }
+}
+
+impl<R: Read> Read for Tracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.last = self.pos;
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.last = self.pos;
+        self.inner.read_exact(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl<R: Seek> Seek for Tracker<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        self.last = self.pos;
+        Ok(self.pos)
+    }
+}
+
+/// looks up a compressed normal in NORMALS.
+/// indices outside of the table give a zero vector
+fn decode_normal(idx: u8) -> vec3_t {
+    NORMALS
+        .get(idx as usize)
+        .copied()
+        .unwrap_or([0.0, 0.0, 0.0])
+}
+
+fn sub(a: vec3_t, b: vec3_t) -> vec3_t {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn add(a: vec3_t, b: vec3_t) -> vec3_t {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: vec3_t, s: f32) -> vec3_t {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: vec3_t, b: vec3_t) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: vec3_t, b: vec3_t) -> vec3_t {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// unit vector of a, None for a zero (or not finite) vector
+fn normalize(a: vec3_t) -> Option<vec3_t> {
+    let len = dot(a, a).sqrt();
+    if len > 0.0 && len.is_finite() {
+        Some(scale(a, 1.0 / len))
+    } else {
+        None
     }
 }
 
@@ -69,8 +627,8 @@ fn to_utf8(bytes: &[u8]) -> std::result::Result<String, std::str::Utf8Error> {
     let utf_str = if let Some(idx) = bytes.iter().enumerate().find(|(_, v)| **v == 0) {
         std::str::from_utf8(&bytes[0..idx.0])?
     } else {
-        std::str::from_utf8(&bytes)?
+        std::str::from_utf8(bytes)?
     };
 
     Ok(utf_str.to_string())
-}
\ No newline at end of file
+}
@@ -1,8 +1,13 @@
 extern crate byteorder;
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use super::{to_utf8, vec3_t, Error, Result};
-use std::io::{Read, Seek, SeekFrom};
+use super::validation::{check_count, check_normal, in_range, Issue, Item};
+use super::{
+    decode_normal, to_utf8, vec3_t, AliasModel, Budget, Error, Face, LoadOptions, Result, Section,
+    SkinRef, Tracker,
+};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
 
 pub const MAX_TRIANGLES: u16 = 4096;
 pub const MAX_VERTICES: u16 = 2048;
@@ -10,6 +15,8 @@ pub const MAX_TEXCOORDS: u16 = 2048;
 pub const MAX_FRAMES: u16 = 512;
 pub const MAX_SKINS: u16 = 32;
 
+/// frame ranges of the stock quake 2 player models.
+/// see animation::clips_from_frames for other models
 pub const ANIMATIONS: [[u8; 3]; 21] = [
     // first, last, fps
     [0, 39, 9],     // STAND
@@ -27,7 +34,7 @@ pub const ANIMATIONS: [[u8; 3]; 21] = [
     [135, 153, 10], // CROUCH_STAND
     [154, 159, 7],  // CROUCH_WALK
     [160, 168, 10], // CROUCH_ATTACK
-    [196, 172, 7],  // CROUCH_PAIN
+    [169, 172, 7],  // CROUCH_PAIN
     [173, 177, 5],  // CROUCH_DEATH
     [178, 183, 7],  // DEATH_FALLBACK
     [184, 189, 7],  // DEATH_FALLFORWARD
@@ -35,32 +42,30 @@ pub const ANIMATIONS: [[u8; 3]; 21] = [
     [198, 198, 5],  // BOOM
 ];
 
-#[allow(non_camel_case_types)]
-pub enum Animation {
-    STAND = 0,
-    RUN,
-    ATTACK,
-    PAIN_A,
-    PAIN_B,
-    PAIN_C,
-    JUMP,
-    FLIP,
-    SALUTE,
-    FALLBACK,
-    WAVE,
-    POINT,
-    CROUCH_STAND,
-    CROUCH_WALK,
-    CROUCH_ATTACK,
-    CROUCH_PAIN,
-    CROUCH_DEATH,
-    DEATH_FALLBACK,
-    DEATH_FALLFORWARD,
-    DEATH_FALLBACKSLOW,
-    BOOM,
-
-    MAX_ANIMATIONS = 198,
-}
+/// names of the ANIMATIONS entries, same order
+pub const ANIMATION_NAMES: [&str; 21] = [
+    "stand",
+    "run",
+    "attack",
+    "pain_a",
+    "pain_b",
+    "pain_c",
+    "jump",
+    "flip",
+    "salute",
+    "fallback",
+    "wave",
+    "point",
+    "crouch_stand",
+    "crouch_walk",
+    "crouch_attack",
+    "crouch_pain",
+    "crouch_death",
+    "death_fallback",
+    "death_fallforward",
+    "death_fallbackslow",
+    "boom",
+];
 
 #[allow(non_camel_case_types)]
 type skin_name_t = [u8; 64];
@@ -92,8 +97,8 @@ enum NextCommand {
 
 pub const HEADER_IDENT: i32 = 844121161;
 pub const HEADER_VERSION: i32 = 8;
+pub const HEADER_SIZE: u64 = 68;
 
-#[repr(C)]
 #[derive(Debug)]
 pub struct Header {
     pub ident: i32,   // IDP2 / 844121161
@@ -150,34 +155,61 @@ pub struct Model {
 }
 
 impl Model {
-    fn read_header(reader: &mut dyn Read) -> Result<Header> {
-        let header = {
-            let mut buf = [0; std::mem::size_of::<Header>()];
-            if let Err(e) = reader.read_exact(&mut buf) {
-                return Err(Error::io(e, "failed to read header"));
-            };
-            let header: Header = unsafe { std::mem::transmute(buf) };
-            header
-        };
+    fn read_i32(reader: &mut dyn Read, msg: &str) -> Result<i32> {
+        reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
 
-        if header.ident != HEADER_IDENT {
-            return Err(Error::ident(header.ident, HEADER_IDENT));
+    fn read_header(reader: &mut dyn Read) -> Result<Header> {
+        let ident = Self::read_i32(reader, "failed to read 'ident'.")?;
+        if ident != HEADER_IDENT {
+            return Err(Error::ident(ident, HEADER_IDENT));
         }
 
-        if header.version != HEADER_VERSION {
-            return Err(Error::version(header.version, HEADER_VERSION));
+        let version = Self::read_i32(reader, "failed to read 'version'.")?;
+        if version != HEADER_VERSION {
+            return Err(Error::version(version, HEADER_VERSION));
         }
 
+        let header = Header {
+            ident: ident,
+            version: version,
+
+            skin_width: Self::read_i32(reader, "failed to read 'skin_width'.")?,
+            skin_height: Self::read_i32(reader, "failed to read 'skin_height'.")?,
+
+            frame_size: Self::read_i32(reader, "failed to read 'frame_size'.")?,
+            num_skins: Self::read_i32(reader, "failed to read 'num_skins'.")?,
+            num_vertices: Self::read_i32(reader, "failed to read 'num_vertices'.")?,
+            num_texcoords: Self::read_i32(reader, "failed to read 'num_texcoords'.")?,
+            num_faces: Self::read_i32(reader, "failed to read 'num_faces'.")?,
+            num_gl_cmds: Self::read_i32(reader, "failed to read 'num_gl_cmds'.")?,
+            num_frames: Self::read_i32(reader, "failed to read 'num_frames'.")?,
+
+            offset_skins: Self::read_i32(reader, "failed to read 'offset_skins'.")?,
+            offset_texcoords: Self::read_i32(reader, "failed to read 'offset_texcoords'.")?,
+            offset_faces: Self::read_i32(reader, "failed to read 'offset_faces'.")?,
+            offset_frames: Self::read_i32(reader, "failed to read 'offset_frames'.")?,
+            offset_gl_cmds: Self::read_i32(reader, "failed to read 'offset_gl_cmds'.")?,
+            offset_end: Self::read_i32(reader, "failed to read 'offset_end'.")?,
+        };
+
         Ok(header)
     }
 
-    fn read_skin_names<T: Read + Seek>(reader: &mut T, header: &Header) -> Result<Vec<String>> {
-        let mut skin_names = Vec::<String>::new();
+    fn read_skin_names<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<String>> {
+        let num_skins = budget.alloc("num_skins", header.num_skins as i64, 64)?;
+        let mut skin_names = Vec::<String>::with_capacity(num_skins);
         reader
             .seek(SeekFrom::Start(header.offset_skins as u64))
             .map_err(|e| Error::io(e, "offset_skins failed."))?;
         let mut buf: skin_name_t = [0; 64];
-        for _ in 0..header.num_skins {
+        for _ in 0..num_skins {
             reader
                 .read_exact(&mut buf)
                 .map_err(|e| Error::io(e, "skin_name: read_exact failed."))?;
@@ -189,12 +221,21 @@ impl Model {
         Ok(skin_names)
     }
 
-    fn read_texcoords<T: Read + Seek>(reader: &mut T, header: &Header) -> Result<Vec<TexCoord>> {
-        let mut texcoords = Vec::<TexCoord>::new();
+    fn read_texcoords<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<TexCoord>> {
+        let num_texcoords = budget.alloc(
+            "num_texcoords",
+            header.num_texcoords as i64,
+            size_of::<TexCoord>(),
+        )?;
+        let mut texcoords = Vec::<TexCoord>::with_capacity(num_texcoords);
         reader
             .seek(SeekFrom::Start(header.offset_texcoords as u64))
             .map_err(|e| Error::io(e, "offset_texcoords failed."))?;
-        for _ in 0..header.num_texcoords {
+        for _ in 0..num_texcoords {
             let s: i16 = reader
                 .read_i16::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read 's'."))?;
@@ -209,13 +250,19 @@ impl Model {
         Ok(texcoords)
     }
 
-    fn read_faces<T: Read + Seek>(reader: &mut T, header: &Header) -> Result<Vec<Triangle>> {
-        let mut faces = Vec::<Triangle>::new();
+    fn read_faces<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Triangle>> {
+        let num_faces =
+            budget.alloc("num_faces", header.num_faces as i64, size_of::<Triangle>())?;
+        let mut faces = Vec::<Triangle>::with_capacity(num_faces);
         reader
             .seek(SeekFrom::Start(header.offset_faces as u64))
             .map_err(|e| Error::io(e, "offset_faces failed."))?;
 
-        for _ in 0..header.num_faces {
+        for _ in 0..num_faces {
             let x = reader
                 .read_u16::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read 'x'."))?;
@@ -245,14 +292,22 @@ impl Model {
         Ok(faces)
     }
 
-    fn read_commands<T: Read + Seek>(reader: &mut T, header: &Header) -> Result<Vec<Command>> {
+    /// packets are pushed as they are read, num_gl_cmds
+    /// is only checked against the budget
+    fn read_commands<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Command>> {
+        let num_gl_cmds =
+            budget.alloc("num_gl_cmds", header.num_gl_cmds as i64, size_of::<i32>())?;
         let mut commands = Vec::<Command>::new();
         reader
             .seek(SeekFrom::Start(header.offset_gl_cmds as u64))
             .map_err(|e| Error::io(e, "offset_gl_cmds failed."))?;
         let mut state = NextCommand::Typ;
         let mut packets = Vec::new();
-        for _ in 0..header.num_gl_cmds {
+        for _ in 0..num_gl_cmds {
             match state {
                 NextCommand::Typ => {
                     let n = reader
@@ -262,9 +317,9 @@ impl Model {
                         break;
                     }
                     state = if n > 0 {
-                        NextCommand::S(CommandType::Fan, n.abs() as u32)
+                        NextCommand::S(CommandType::Fan, n.unsigned_abs())
                     } else {
-                        NextCommand::S(CommandType::Strip, n.abs() as u32)
+                        NextCommand::S(CommandType::Strip, n.unsigned_abs())
                     };
                 }
                 NextCommand::S(typ, n) => {
@@ -289,7 +344,7 @@ impl Model {
                     state = if n - 1 == 0 {
                         let command = Command {
                             typ: typ,
-                            packets: std::mem::replace(&mut packets, Vec::<CommandPacket>::new()),
+                            packets: std::mem::take(&mut packets),
                         };
                         commands.push(command);
                         NextCommand::Typ
@@ -303,13 +358,18 @@ impl Model {
         Ok(commands)
     }
 
-    fn read_frames<T: Read + Seek>(reader: &mut T, header: &Header) -> Result<Vec<Frame>> {
-        let mut frames = Vec::<Frame>::new();
+    fn read_frames<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Frame>> {
+        let num_frames = budget.frames(header.num_frames as i64, size_of::<Frame>())?;
+        let mut frames = Vec::<Frame>::with_capacity(num_frames);
         reader
             .seek(SeekFrom::Start(header.offset_frames as u64))
             .map_err(|e| Error::io(e, "offset_frames failed."))?;
         let mut buf = [0; 16];
-        for _ in 0..header.num_frames {
+        for _ in 0..num_frames {
             let scale = {
                 let x = reader
                     .read_f32::<LittleEndian>()
@@ -340,8 +400,13 @@ impl Model {
                 .map_err(|e| Error::io(e, "failed to read 'frame name'."))?;
             let name = to_utf8(&buf)
                 .map_err(|e| Error::utf8(e, "failed to convert frame name to utf8"))?;
-            let mut vertices = Vec::<Vertex>::with_capacity(header.num_vertices as usize);
-            for _ in 0..header.num_vertices {
+            let num_vertices = budget.alloc(
+                "num_vertices",
+                header.num_vertices as i64,
+                size_of::<Vertex>(),
+            )?;
+            let mut vertices = Vec::<Vertex>::with_capacity(num_vertices);
+            for _ in 0..num_vertices {
                 let v = {
                     let x = reader
                         .read_u8()
@@ -379,20 +444,372 @@ impl Model {
     }
 
     pub fn from_reader<T: Read + Seek>(reader: &mut T) -> Result<Self> {
-        let header = Self::read_header(reader)?;
-        let skin_names = Self::read_skin_names(reader, &header)?;
-        let texcoords = Self::read_texcoords(reader, &header)?;
-        let faces = Self::read_faces(reader, &header)?;
-        let commands = Self::read_commands(reader, &header)?;
-        let frames = Self::read_frames(reader, &header)?;
-
-        Ok(Model {
+        Self::from_reader_with(reader, &LoadOptions::default())
+    }
+
+    pub fn from_reader_with<T: Read + Seek>(reader: &mut T, options: &LoadOptions) -> Result<Self> {
+        let mut reader = Tracker::seekable(reader)?;
+        let mut budget = Budget::new(&options.limits);
+        let header =
+            Self::read_header(&mut reader).map_err(|e| e.at(Section::Header, reader.last))?;
+        let skin_names = Self::read_skin_names(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Skins, reader.last))?;
+        let texcoords = Self::read_texcoords(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Texcoords, reader.last))?;
+        let faces = Self::read_faces(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Triangles, reader.last))?;
+        let commands = Self::read_commands(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Commands, reader.last))?;
+        let frames = Self::read_frames(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Frames, reader.last))?;
+
+        let model = Model {
             header: header,
             skin_names: skin_names,
             texcoords: texcoords,
             faces: faces,
             frames: frames,
             commands: commands,
-        })
-    }    
+        };
+        if options.strict {
+            Error::check(model.validate())?;
+        }
+        Ok(model)
+    }
+
+    /// checks counts and indices. a model without
+    /// issues can be converted without panics
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::<Issue>::new();
+        let num_vertices = self.num_vertices();
+        let num_texcoords = self.texcoords.len();
+        check_count(&mut issues, Item::Skins, self.skin_names.len(), MAX_SKINS);
+        check_count(&mut issues, Item::Vertices, num_vertices, MAX_VERTICES);
+        check_count(&mut issues, Item::Texcoords, num_texcoords, MAX_TEXCOORDS);
+        check_count(
+            &mut issues,
+            Item::Triangles,
+            self.faces.len(),
+            MAX_TRIANGLES,
+        );
+        check_count(&mut issues, Item::Frames, self.frames.len(), MAX_FRAMES);
+
+        for (i, face) in self.faces.iter().enumerate() {
+            for j in 0..3 {
+                if !in_range(face.vertex[j] as i64, num_vertices) {
+                    issues.push(Issue::VertexIndex {
+                        triangle: i,
+                        index: face.vertex[j] as i64,
+                        len: num_vertices,
+                    });
+                }
+                if !in_range(face.st_idx[j] as i64, num_texcoords) {
+                    issues.push(Issue::TexcoordIndex {
+                        triangle: i,
+                        index: face.st_idx[j] as i64,
+                        len: num_texcoords,
+                    });
+                }
+            }
+        }
+
+        if self.frames.is_empty() {
+            issues.push(Issue::NoFrames);
+        }
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            if frame.vertices.len() != num_vertices {
+                issues.push(Issue::VertexCount {
+                    frame: i,
+                    count: frame.vertices.len(),
+                    expected: num_vertices,
+                });
+            }
+            for (v, vertex) in frame.vertices.iter().enumerate() {
+                check_normal(&mut issues, i, v, vertex.normal_idx);
+            }
+        }
+
+        for (i, command) in self.commands.iter().enumerate() {
+            for packet in &command.packets {
+                if !in_range(packet.i as i64, num_vertices) {
+                    issues.push(Issue::CommandIndex {
+                        command: i,
+                        index: packet.i as i64,
+                        len: num_vertices,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+
+    fn write_i32<W: Write>(writer: &mut W, value: i32, msg: &str) -> Result<()> {
+        writer
+            .write_i32::<LittleEndian>(value)
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn write_f32<W: Write>(writer: &mut W, value: f32, msg: &str) -> Result<()> {
+        writer
+            .write_f32::<LittleEndian>(value)
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    /// writes name zero padded to len bytes
+    fn write_name<W: Write>(writer: &mut W, name: &str, len: usize, msg: &str) -> Result<()> {
+        let bytes = name.as_bytes();
+        if bytes.len() > len {
+            return Err(Error::unsupported(&format!(
+                "{} '{}' is longer than {} bytes.",
+                msg, name, len
+            )));
+        }
+        let mut buf = vec![0; len];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        writer.write_all(&buf).map_err(|e| Error::io(e, msg))
+    }
+
+    fn position<W: Seek>(writer: &mut W, start: u64) -> Result<i32> {
+        let pos = writer
+            .stream_position()
+            .map_err(|e| Error::io(e, "failed to get stream position."))?;
+        Ok((pos - start) as i32)
+    }
+
+    fn write_header<W: Write>(writer: &mut W, header: &Header) -> Result<()> {
+        let fields = [
+            header.ident,
+            header.version,
+            header.skin_width,
+            header.skin_height,
+            header.frame_size,
+            header.num_skins,
+            header.num_vertices,
+            header.num_texcoords,
+            header.num_faces,
+            header.num_gl_cmds,
+            header.num_frames,
+            header.offset_skins,
+            header.offset_texcoords,
+            header.offset_faces,
+            header.offset_frames,
+            header.offset_gl_cmds,
+            header.offset_end,
+        ];
+        for field in fields.iter() {
+            Self::write_i32(writer, *field, "failed to write header.")?;
+        }
+        Ok(())
+    }
+
+    fn write_skin_names<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for name in &self.skin_names {
+            Self::write_name(writer, name, 64, "skin name")?;
+        }
+        Ok(())
+    }
+
+    fn write_texcoords<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for st in &self.texcoords {
+            writer
+                .write_i16::<LittleEndian>(st.s)
+                .map_err(|e| Error::io(e, "failed to write 's'."))?;
+            writer
+                .write_i16::<LittleEndian>(st.t)
+                .map_err(|e| Error::io(e, "failed to write 't'."))?;
+        }
+        Ok(())
+    }
+
+    fn write_faces<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for face in &self.faces {
+            for idx in face.vertex.iter().chain(face.st_idx.iter()) {
+                writer
+                    .write_u16::<LittleEndian>(*idx)
+                    .map_err(|e| Error::io(e, "failed to write triangle."))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_frames<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for frame in &self.frames {
+            for v in frame.scale.iter() {
+                Self::write_f32(writer, *v, "failed to write 'scale'.")?;
+            }
+            for v in frame.translate.iter() {
+                Self::write_f32(writer, *v, "failed to write 'translate'.")?;
+            }
+            Self::write_name(writer, &frame.name, 16, "frame name")?;
+            for vertex in &frame.vertices {
+                writer
+                    .write_all(&vertex.v)
+                    .map_err(|e| Error::io(e, "failed to write vertex."))?;
+                writer
+                    .write_u8(vertex.normal_idx)
+                    .map_err(|e| Error::io(e, "failed to write vertex."))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// returns the number of written i32 values,
+    /// including the terminating 0
+    fn write_commands<W: Write>(&self, writer: &mut W) -> Result<i32> {
+        let mut count = 0;
+        for command in &self.commands {
+            let n = command.packets.len() as i32;
+            let n = match command.typ {
+                CommandType::Fan => n,
+                CommandType::Strip => -n,
+            };
+            Self::write_i32(writer, n, "failed to write 'n'.")?;
+            for packet in &command.packets {
+                Self::write_f32(writer, packet.s, "failed to write 's'.")?;
+                Self::write_f32(writer, packet.t, "failed to write 't'.")?;
+                Self::write_i32(writer, packet.i, "failed to write 'i'.")?;
+            }
+            count += 1 + 3 * command.packets.len() as i32;
+        }
+        Self::write_i32(writer, 0, "failed to write 'n'.")?;
+        Ok(count + 1)
+    }
+
+    /// writes the model as md2. the counts, offset_* fields and
+    /// frame_size of the header are computed, the sections are
+    /// written in the usual order: skins, texcoords, faces,
+    /// frames, gl commands.
+    /// names are zero padded. bytes after the terminating zero of
+    /// a name are not kept when reading, so a file with garbage in
+    /// those bytes does not round trip byte for byte
+    pub fn write_to<W: Write + Seek>(&self, writer: &mut W) -> Result<()> {
+        let start = writer
+            .stream_position()
+            .map_err(|e| Error::io(e, "failed to get stream position."))?;
+        writer
+            .seek(SeekFrom::Start(start + HEADER_SIZE))
+            .map_err(|e| Error::io(e, "failed to skip header."))?;
+
+        let offset_skins = Self::position(writer, start)?;
+        self.write_skin_names(writer)?;
+        let offset_texcoords = Self::position(writer, start)?;
+        self.write_texcoords(writer)?;
+        let offset_faces = Self::position(writer, start)?;
+        self.write_faces(writer)?;
+        let offset_frames = Self::position(writer, start)?;
+        self.write_frames(writer)?;
+        let offset_gl_cmds = Self::position(writer, start)?;
+        let num_gl_cmds = self.write_commands(writer)?;
+        let offset_end = Self::position(writer, start)?;
+
+        let num_vertices = match self.frames.first() {
+            Some(frame) => frame.vertices.len() as i32,
+            None => self.header.num_vertices,
+        };
+        let header = Header {
+            ident: HEADER_IDENT,
+            version: HEADER_VERSION,
+            skin_width: self.header.skin_width,
+            skin_height: self.header.skin_height,
+            frame_size: 40 + 4 * num_vertices,
+            num_skins: self.skin_names.len() as i32,
+            num_vertices: num_vertices,
+            num_texcoords: self.texcoords.len() as i32,
+            num_faces: self.faces.len() as i32,
+            num_gl_cmds: num_gl_cmds,
+            num_frames: self.frames.len() as i32,
+            offset_skins: offset_skins,
+            offset_texcoords: offset_texcoords,
+            offset_faces: offset_faces,
+            offset_frames: offset_frames,
+            offset_gl_cmds: offset_gl_cmds,
+            offset_end: offset_end,
+        };
+
+        writer
+            .seek(SeekFrom::Start(start))
+            .map_err(|e| Error::io(e, "failed to seek to header."))?;
+        Self::write_header(writer, &header)?;
+        writer
+            .seek(SeekFrom::Start(start + offset_end as u64))
+            .map_err(|e| Error::io(e, "failed to seek to end."))?;
+        Ok(())
+    }
+}
+
+impl AliasModel for Model {
+    fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn frame_name(&self, frame: usize) -> &str {
+        &self.frames[frame].name
+    }
+
+    fn num_vertices(&self) -> usize {
+        match self.frames.first() {
+            Some(frame) => frame.vertices.len(),
+            None => self.header.num_vertices.max(0) as usize,
+        }
+    }
+
+    fn positions(&self, frame: usize) -> Vec<vec3_t> {
+        let frame = &self.frames[frame];
+        let scale = frame.scale;
+        let translate = frame.translate;
+        frame
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let x = (vertex.v[0] as f32 * scale[0]) + translate[0];
+                let y = (vertex.v[1] as f32 * scale[1]) + translate[1];
+                let z = (vertex.v[2] as f32 * scale[2]) + translate[2];
+                [x, y, z]
+            })
+            .collect()
+    }
+
+    fn normals(&self, frame: usize) -> Vec<vec3_t> {
+        self.frames[frame]
+            .vertices
+            .iter()
+            .map(|vertex| decode_normal(vertex.normal_idx))
+            .collect()
+    }
+
+    fn faces(&self) -> Vec<Face> {
+        self.faces
+            .iter()
+            .map(|face| Face {
+                vertex: [
+                    face.vertex[0] as usize,
+                    face.vertex[1] as usize,
+                    face.vertex[2] as usize,
+                ],
+                texcoord: [
+                    face.st_idx[0] as usize,
+                    face.st_idx[1] as usize,
+                    face.st_idx[2] as usize,
+                ],
+            })
+            .collect()
+    }
+
+    fn texcoords(&self) -> Vec<(f32, f32)> {
+        let w = self.header.skin_width as f32;
+        let h = self.header.skin_height as f32;
+        self.texcoords
+            .iter()
+            .map(|st| (st.s as f32 / w, st.t as f32 / h))
+            .collect()
+    }
+
+    fn skins(&self) -> Vec<SkinRef<'_>> {
+        self.skin_names
+            .iter()
+            .map(|name| SkinRef::Path(name))
+            .collect()
+    }
 }
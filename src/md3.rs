@@ -0,0 +1,651 @@
+extern crate byteorder;
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use super::validation::{check_count, in_range, Issue, Item};
+use super::{
+    to_utf8, vec3_t, AliasModel, Budget, Error, Face, LoadOptions, Result, Section, SkinRef,
+    Tracker,
+};
+use std::io::{Read, Seek, SeekFrom};
+use std::mem::size_of;
+
+pub const MAX_FRAMES: u16 = 1024;
+pub const MAX_TAGS: u16 = 16;
+pub const MAX_SURFACES: u16 = 32;
+pub const MAX_SHADERS: u16 = 256;
+pub const MAX_VERTICES: u16 = 4096;
+pub const MAX_TRIANGLES: u16 = 8192;
+
+/// multiply packed xyz values by this to get the real position
+pub const XYZ_SCALE: f32 = 1.0 / 64.0;
+
+pub const HEADER_IDENT: i32 = 860898377;
+pub const HEADER_VERSION: i32 = 15;
+
+#[allow(non_camel_case_types)]
+type qpath_t = [u8; 64];
+
+#[derive(Debug)]
+pub struct Header {
+    pub ident: i32,   // IDP3 / 860898377
+    pub version: i32, // 15
+    pub name: String,
+    pub flags: i32,
+
+    pub num_frames: i32,
+    pub num_tags: i32,
+    pub num_surfaces: i32,
+    pub num_skins: i32, // unused
+
+    pub offset_frames: i32,
+    pub offset_tags: i32,
+    pub offset_surfaces: i32,
+    pub offset_end: i32,
+}
+
+pub struct Frame {
+    pub min_bounds: vec3_t,
+    pub max_bounds: vec3_t,
+    pub local_origin: vec3_t,
+    pub radius: f32,
+    pub name: String,
+}
+
+/// attachment point. there are num_tags tags for each frame,
+/// stored frame by frame
+pub struct Tag {
+    pub name: String,
+    pub origin: vec3_t,
+    pub axis: [vec3_t; 3],
+}
+
+pub struct Shader {
+    pub name: String,
+    pub shader_idx: i32,
+}
+
+pub struct Triangle {
+    pub vertex: [i32; 3], // index to Surface::vertices of a frame
+}
+
+pub struct TexCoord {
+    pub s: f32,
+    pub t: f32,
+}
+
+pub struct Vertex {
+    pub v: [i16; 3], // to uncompress: real[i] = v[i] * XYZ_SCALE
+    pub normal: i16, // latitude and longitude packed into hi and lo byte
+}
+
+impl Vertex {
+    pub fn position(&self) -> vec3_t {
+        [
+            self.v[0] as f32 * XYZ_SCALE,
+            self.v[1] as f32 * XYZ_SCALE,
+            self.v[2] as f32 * XYZ_SCALE,
+        ]
+    }
+
+    /// decodes the spherical coordinates stored in `normal`
+    pub fn normal(&self) -> vec3_t {
+        let lat = ((self.normal >> 8) & 0xff) as f32 * (2.0 * std::f32::consts::PI) / 255.0;
+        let lng = (self.normal & 0xff) as f32 * (2.0 * std::f32::consts::PI) / 255.0;
+        [lat.cos() * lng.sin(), lat.sin() * lng.sin(), lng.cos()]
+    }
+}
+
+/// all offsets of a surface header are relative
+/// to the start of the surface
+#[derive(Debug)]
+pub struct SurfaceHeader {
+    pub ident: i32, // IDP3
+    pub name: String,
+    pub flags: i32,
+
+    pub num_frames: i32, // same as Header::num_frames
+    pub num_shaders: i32,
+    pub num_vertices: i32,
+    pub num_triangles: i32,
+
+    pub offset_triangles: i32,
+    pub offset_shaders: i32,
+    pub offset_st: i32,
+    pub offset_xyznormal: i32,
+    pub offset_end: i32,
+}
+
+pub struct Surface {
+    pub header: SurfaceHeader,
+    pub shaders: Vec<Shader>,
+    pub triangles: Vec<Triangle>,
+    pub texcoords: Vec<TexCoord>,   // one for each vertex
+    pub vertices: Vec<Vec<Vertex>>, // list of frames. each frame has num_vertices vertices
+}
+
+pub struct Model {
+    pub header: Header,
+    pub frames: Vec<Frame>,
+    pub tags: Vec<Tag>,
+    pub surfaces: Vec<Surface>,
+}
+
+impl Model {
+    fn read_vec3<T: Read>(reader: &mut T, msg: &str) -> Result<vec3_t> {
+        let x = reader
+            .read_f32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))?;
+        let y = reader
+            .read_f32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))?;
+        let z = reader
+            .read_f32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))?;
+        Ok([x, y, z])
+    }
+
+    fn read_name<T: Read>(reader: &mut T, msg: &str) -> Result<String> {
+        let mut buf: qpath_t = [0; 64];
+        reader.read_exact(&mut buf).map_err(|e| Error::io(e, msg))?;
+        to_utf8(&buf).map_err(|e| Error::utf8(e, msg))
+    }
+
+    fn read_header<T: Read>(reader: &mut T) -> Result<Header> {
+        let ident = reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, "failed to read 'ident'."))?;
+        if ident != HEADER_IDENT {
+            return Err(Error::ident(ident, HEADER_IDENT));
+        }
+
+        let version = reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, "failed to read 'version'."))?;
+        if version != HEADER_VERSION {
+            return Err(Error::version(version, HEADER_VERSION));
+        }
+
+        let name = Self::read_name(reader, "failed to read model name.")?;
+
+        let mut fields = [0i32; 9];
+        reader
+            .read_i32_into::<LittleEndian>(&mut fields)
+            .map_err(|e| Error::io(e, "failed to read header"))?;
+
+        Ok(Header {
+            ident: ident,
+            version: version,
+            name: name,
+            flags: fields[0],
+            num_frames: fields[1],
+            num_tags: fields[2],
+            num_surfaces: fields[3],
+            num_skins: fields[4],
+            offset_frames: fields[5],
+            offset_tags: fields[6],
+            offset_surfaces: fields[7],
+            offset_end: fields[8],
+        })
+    }
+
+    fn read_frames<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Frame>> {
+        let num_frames = budget.frames(header.num_frames as i64, size_of::<Frame>())?;
+        let mut frames = Vec::<Frame>::with_capacity(num_frames);
+        reader
+            .seek(SeekFrom::Start(header.offset_frames as u64))
+            .map_err(|e| Error::io(e, "offset_frames failed."))?;
+        let mut buf = [0; 16];
+        for _ in 0..num_frames {
+            let min_bounds = Self::read_vec3(reader, "failed to read 'min_bounds'.")?;
+            let max_bounds = Self::read_vec3(reader, "failed to read 'max_bounds'.")?;
+            let local_origin = Self::read_vec3(reader, "failed to read 'local_origin'.")?;
+            let radius = reader
+                .read_f32::<LittleEndian>()
+                .map_err(|e| Error::io(e, "failed to read 'radius'."))?;
+            reader
+                .read_exact(&mut buf)
+                .map_err(|e| Error::io(e, "failed to read 'frame name'."))?;
+            let name = to_utf8(&buf)
+                .map_err(|e| Error::utf8(e, "failed to convert frame name to utf8"))?;
+
+            let frame = Frame {
+                min_bounds: min_bounds,
+                max_bounds: max_bounds,
+                local_origin: local_origin,
+                radius: radius,
+                name: name,
+            };
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn read_tags<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Tag>> {
+        let num_tags = budget.alloc(
+            "num_frames * num_tags",
+            header.num_frames as i64 * header.num_tags as i64,
+            size_of::<Tag>(),
+        )?;
+        let mut tags = Vec::<Tag>::with_capacity(num_tags);
+        reader
+            .seek(SeekFrom::Start(header.offset_tags as u64))
+            .map_err(|e| Error::io(e, "offset_tags failed."))?;
+        for _ in 0..num_tags {
+            let name = Self::read_name(reader, "failed to read 'tag name'.")?;
+            let origin = Self::read_vec3(reader, "failed to read 'tag origin'.")?;
+            let axis = [
+                Self::read_vec3(reader, "failed to read 'tag axis'.")?,
+                Self::read_vec3(reader, "failed to read 'tag axis'.")?,
+                Self::read_vec3(reader, "failed to read 'tag axis'.")?,
+            ];
+
+            let tag = Tag {
+                name: name,
+                origin: origin,
+                axis: axis,
+            };
+            tags.push(tag);
+        }
+
+        Ok(tags)
+    }
+
+    fn read_surface_header<T: Read>(reader: &mut T) -> Result<SurfaceHeader> {
+        let ident = reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, "failed to read surface 'ident'."))?;
+        if ident != HEADER_IDENT {
+            return Err(Error::ident(ident, HEADER_IDENT));
+        }
+
+        let name = Self::read_name(reader, "failed to read surface name.")?;
+
+        let mut fields = [0i32; 10];
+        reader
+            .read_i32_into::<LittleEndian>(&mut fields)
+            .map_err(|e| Error::io(e, "failed to read surface header"))?;
+
+        Ok(SurfaceHeader {
+            ident: ident,
+            name: name,
+            flags: fields[0],
+            num_frames: fields[1],
+            num_shaders: fields[2],
+            num_vertices: fields[3],
+            num_triangles: fields[4],
+            offset_triangles: fields[5],
+            offset_shaders: fields[6],
+            offset_st: fields[7],
+            offset_xyznormal: fields[8],
+            offset_end: fields[9],
+        })
+    }
+
+    /// offsets of a surface are relative to its start
+    fn surface_offset(start: u64, offset: i32, name: &str) -> Result<u64> {
+        let target = if offset < 0 {
+            start.checked_sub(-(offset as i64) as u64)
+        } else {
+            start.checked_add(offset as u64)
+        };
+        target
+            .ok_or_else(|| Error::unsupported(&format!("invalid surface '{}': {}.", name, offset)))
+    }
+
+    fn read_shaders<T: Read + Seek>(
+        reader: &mut T,
+        start: u64,
+        header: &SurfaceHeader,
+        budget: &mut Budget,
+    ) -> Result<Vec<Shader>> {
+        let num_shaders = budget.alloc(
+            "num_shaders",
+            header.num_shaders as i64,
+            size_of::<Shader>(),
+        )?;
+        let mut shaders = Vec::<Shader>::with_capacity(num_shaders);
+        reader
+            .seek(SeekFrom::Start(Self::surface_offset(
+                start,
+                header.offset_shaders,
+                "offset_shaders",
+            )?))
+            .map_err(|e| Error::io(e, "offset_shaders failed."))?;
+        for _ in 0..num_shaders {
+            let name = Self::read_name(reader, "failed to read 'shader name'.")?;
+            let shader_idx = reader
+                .read_i32::<LittleEndian>()
+                .map_err(|e| Error::io(e, "failed to read 'shader_idx'."))?;
+
+            let shader = Shader {
+                name: name,
+                shader_idx: shader_idx,
+            };
+            shaders.push(shader);
+        }
+
+        Ok(shaders)
+    }
+
+    fn read_triangles<T: Read + Seek>(
+        reader: &mut T,
+        start: u64,
+        header: &SurfaceHeader,
+        budget: &mut Budget,
+    ) -> Result<Vec<Triangle>> {
+        let num_triangles = budget.alloc(
+            "num_triangles",
+            header.num_triangles as i64,
+            size_of::<Triangle>(),
+        )?;
+        let mut triangles = Vec::<Triangle>::with_capacity(num_triangles);
+        reader
+            .seek(SeekFrom::Start(Self::surface_offset(
+                start,
+                header.offset_triangles,
+                "offset_triangles",
+            )?))
+            .map_err(|e| Error::io(e, "offset_triangles failed."))?;
+        for _ in 0..num_triangles {
+            let mut vertex = [0i32; 3];
+            reader
+                .read_i32_into::<LittleEndian>(&mut vertex)
+                .map_err(|e| Error::io(e, "failed to read triangle."))?;
+
+            triangles.push(Triangle { vertex: vertex });
+        }
+
+        Ok(triangles)
+    }
+
+    fn read_texcoords<T: Read + Seek>(
+        reader: &mut T,
+        start: u64,
+        header: &SurfaceHeader,
+        budget: &mut Budget,
+    ) -> Result<Vec<TexCoord>> {
+        let num_vertices = budget.alloc(
+            "num_vertices",
+            header.num_vertices as i64,
+            size_of::<TexCoord>(),
+        )?;
+        let mut texcoords = Vec::<TexCoord>::with_capacity(num_vertices);
+        reader
+            .seek(SeekFrom::Start(Self::surface_offset(
+                start,
+                header.offset_st,
+                "offset_st",
+            )?))
+            .map_err(|e| Error::io(e, "offset_st failed."))?;
+        for _ in 0..num_vertices {
+            let s = reader
+                .read_f32::<LittleEndian>()
+                .map_err(|e| Error::io(e, "failed to read 's'."))?;
+            let t = reader
+                .read_f32::<LittleEndian>()
+                .map_err(|e| Error::io(e, "failed to read 't'."))?;
+
+            texcoords.push(TexCoord { s: s, t: t });
+        }
+
+        Ok(texcoords)
+    }
+
+    fn read_vertices<T: Read + Seek>(
+        reader: &mut T,
+        start: u64,
+        header: &SurfaceHeader,
+        budget: &mut Budget,
+    ) -> Result<Vec<Vec<Vertex>>> {
+        let num_frames = budget.alloc(
+            "surface num_frames",
+            header.num_frames as i64,
+            size_of::<Vec<Vertex>>(),
+        )?;
+        let mut frames = Vec::<Vec<Vertex>>::with_capacity(num_frames);
+        reader
+            .seek(SeekFrom::Start(Self::surface_offset(
+                start,
+                header.offset_xyznormal,
+                "offset_xyznormal",
+            )?))
+            .map_err(|e| Error::io(e, "offset_xyznormal failed."))?;
+        for _ in 0..num_frames {
+            let num_vertices = budget.alloc(
+                "num_vertices",
+                header.num_vertices as i64,
+                size_of::<Vertex>(),
+            )?;
+            let mut vertices = Vec::<Vertex>::with_capacity(num_vertices);
+            for _ in 0..num_vertices {
+                let mut v = [0i16; 3];
+                reader
+                    .read_i16_into::<LittleEndian>(&mut v)
+                    .map_err(|e| Error::io(e, "failed to read 'xyz'."))?;
+                let normal = reader
+                    .read_i16::<LittleEndian>()
+                    .map_err(|e| Error::io(e, "failed to read 'normal'."))?;
+
+                vertices.push(Vertex {
+                    v: v,
+                    normal: normal,
+                });
+            }
+            frames.push(vertices);
+        }
+
+        Ok(frames)
+    }
+
+    fn read_surfaces<T: Read + Seek>(
+        reader: &mut T,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Surface>> {
+        let num_surfaces = budget.alloc(
+            "num_surfaces",
+            header.num_surfaces as i64,
+            size_of::<Surface>(),
+        )?;
+        let mut surfaces = Vec::<Surface>::with_capacity(num_surfaces);
+        let mut start = header.offset_surfaces as u64;
+        for _ in 0..num_surfaces {
+            reader
+                .seek(SeekFrom::Start(start))
+                .map_err(|e| Error::io(e, "offset_surfaces failed."))?;
+            let surface_header = Self::read_surface_header(reader)?;
+            let shaders = Self::read_shaders(reader, start, &surface_header, budget)?;
+            let triangles = Self::read_triangles(reader, start, &surface_header, budget)?;
+            let texcoords = Self::read_texcoords(reader, start, &surface_header, budget)?;
+            let vertices = Self::read_vertices(reader, start, &surface_header, budget)?;
+
+            start = Self::surface_offset(start, surface_header.offset_end, "offset_end")?;
+            let surface = Surface {
+                header: surface_header,
+                shaders: shaders,
+                triangles: triangles,
+                texcoords: texcoords,
+                vertices: vertices,
+            };
+            surfaces.push(surface);
+        }
+
+        Ok(surfaces)
+    }
+
+    pub fn from_reader<T: Read + Seek>(reader: &mut T) -> Result<Self> {
+        Self::from_reader_with(reader, &LoadOptions::default())
+    }
+
+    pub fn from_reader_with<T: Read + Seek>(reader: &mut T, options: &LoadOptions) -> Result<Self> {
+        let mut reader = Tracker::seekable(reader)?;
+        let mut budget = Budget::new(&options.limits);
+        let header =
+            Self::read_header(&mut reader).map_err(|e| e.at(Section::Header, reader.last))?;
+        let frames = Self::read_frames(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Frames, reader.last))?;
+        let tags = Self::read_tags(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Tags, reader.last))?;
+        let surfaces = Self::read_surfaces(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Surfaces, reader.last))?;
+
+        let model = Model {
+            header: header,
+            frames: frames,
+            tags: tags,
+            surfaces: surfaces,
+        };
+        if options.strict {
+            Error::check(model.validate())?;
+        }
+        Ok(model)
+    }
+
+    /// checks counts and indices. a model without issues can be
+    /// converted without panics. triangle indices count across
+    /// surfaces, vertex counts of frames are per surface
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::<Issue>::new();
+        check_count(&mut issues, Item::Frames, self.frames.len(), MAX_FRAMES);
+        check_count(
+            &mut issues,
+            Item::Tags,
+            self.header.num_tags.max(0) as usize,
+            MAX_TAGS,
+        );
+        check_count(
+            &mut issues,
+            Item::Surfaces,
+            self.surfaces.len(),
+            MAX_SURFACES,
+        );
+
+        if self.frames.is_empty() {
+            issues.push(Issue::NoFrames);
+        }
+
+        let mut first_triangle = 0;
+        for (i, surface) in self.surfaces.iter().enumerate() {
+            let num_vertices = surface.texcoords.len();
+            check_count(
+                &mut issues,
+                Item::Shaders,
+                surface.shaders.len(),
+                MAX_SHADERS,
+            );
+            check_count(&mut issues, Item::Vertices, num_vertices, MAX_VERTICES);
+            check_count(
+                &mut issues,
+                Item::Triangles,
+                surface.triangles.len(),
+                MAX_TRIANGLES,
+            );
+
+            for (t, triangle) in surface.triangles.iter().enumerate() {
+                for &index in &triangle.vertex {
+                    if !in_range(index as i64, num_vertices) {
+                        issues.push(Issue::VertexIndex {
+                            triangle: first_triangle + t,
+                            index: index as i64,
+                            len: num_vertices,
+                        });
+                    }
+                }
+            }
+            first_triangle += surface.triangles.len();
+
+            if surface.vertices.len() != self.frames.len() {
+                issues.push(Issue::SurfaceFrames {
+                    surface: i,
+                    count: surface.vertices.len(),
+                    expected: self.frames.len(),
+                });
+            }
+            for (f, vertices) in surface.vertices.iter().enumerate() {
+                if vertices.len() != num_vertices {
+                    issues.push(Issue::VertexCount {
+                        frame: f,
+                        count: vertices.len(),
+                        expected: num_vertices,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// all surfaces are merged into one mesh. the vertices of a
+/// surface follow the vertices of the previous surfaces
+impl AliasModel for Model {
+    fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn frame_name(&self, frame: usize) -> &str {
+        &self.frames[frame].name
+    }
+
+    fn num_vertices(&self) -> usize {
+        self.surfaces.iter().map(|s| s.texcoords.len()).sum()
+    }
+
+    fn positions(&self, frame: usize) -> Vec<vec3_t> {
+        self.surfaces
+            .iter()
+            .flat_map(|s| s.vertices[frame].iter().map(|v| v.position()))
+            .collect()
+    }
+
+    fn normals(&self, frame: usize) -> Vec<vec3_t> {
+        self.surfaces
+            .iter()
+            .flat_map(|s| s.vertices[frame].iter().map(|v| v.normal()))
+            .collect()
+    }
+
+    /// every vertex has its own texcoord, so both indices are the same
+    fn faces(&self) -> Vec<Face> {
+        let mut faces = Vec::<Face>::new();
+        let mut offset = 0;
+        for surface in &self.surfaces {
+            for triangle in &surface.triangles {
+                let vertex = [
+                    offset + triangle.vertex[0] as usize,
+                    offset + triangle.vertex[1] as usize,
+                    offset + triangle.vertex[2] as usize,
+                ];
+                faces.push(Face {
+                    vertex: vertex,
+                    texcoord: vertex,
+                });
+            }
+            offset += surface.texcoords.len();
+        }
+        faces
+    }
+
+    fn texcoords(&self) -> Vec<(f32, f32)> {
+        self.surfaces
+            .iter()
+            .flat_map(|s| s.texcoords.iter().map(|st| (st.s, st.t)))
+            .collect()
+    }
+
+    fn skins(&self) -> Vec<SkinRef<'_>> {
+        self.surfaces
+            .iter()
+            .flat_map(|s| s.shaders.iter().map(|shader| SkinRef::Path(&shader.name)))
+            .collect()
+    }
+}
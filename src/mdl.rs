@@ -1,8 +1,15 @@
 extern crate byteorder;
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Read, Write};
+use std::mem::size_of;
 
-use super::{to_utf8, vec3_t, Error, Result};
+use super::image::Image;
+use super::palette::Palette;
+use super::validation::{check_count, check_normal, in_range, Issue, Item};
+use super::{
+    decode_normal, to_utf8, vec3_t, AliasModel, Budget, Error, Face, LoadOptions, Result, Section,
+    SkinRef, Tracker,
+};
 
 pub const MAX_TRIANGLES: u16 = 2048;
 pub const MAX_VERTICES: u16 = 1024;
@@ -12,7 +19,10 @@ pub const MAX_FRAMES: u16 = 256;
 pub const HEADER_IDENT: i32 = 1330660425;
 pub const HEADER_VERSION: i32 = 6;
 
-#[repr(C)]
+/// values of Header::synctype
+pub const ST_SYNC: i32 = 0;
+pub const ST_RAND: i32 = 1;
+
 #[derive(Debug)]
 pub struct Header {
     pub ident: i32,   // must be equal to 1330660425 or to the string “IDPO”
@@ -38,21 +48,24 @@ pub struct Header {
 /// basicaly a bitmap
 /// width and height are stored in header
 /// each item of data vector is an index to
-/// color map super::COLORMAP
-pub struct Skin {
-    pub group: i32, // 0
+/// a palette, usually super::COLORMAP
+pub struct SimpleSkin {
     pub data: Vec<u8>,
 }
 
-// TODO: implement this
-#[allow(dead_code)]
+/// animated skin. time holds the interval
+/// of each image
 pub struct GroupSkin {
-    pub group: i32, // 1
     pub nb: i32,
     pub time: Vec<f32>,
     pub data: Vec<u8>, // nb * skin_width * skin_height
 }
 
+pub enum Skin {
+    Simple(SimpleSkin), // group 0
+    Group(GroupSkin),   // group 1
+}
+
 /// onseam > 0 means the coordinate is on the edge
 /// between front and back parts of the texture
 /// if the triangle is on the back (facefront = 0)
@@ -80,21 +93,72 @@ pub struct SimpleFrame {
     pub verts: Vec<Vertex>,
 }
 
-pub struct Frame {
-    pub type_: i32, // if 0
-    pub frame: SimpleFrame,
-}
-
-// TODO: implement this
-#[allow(dead_code)]
+/// animated frame. time holds the interval
+/// of each subframe
 pub struct GroupFrame {
-    pub type_: i32, // if !0
     pub min: Vertex,
     pub max: Vertex,
     pub time: Vec<f32>,
     pub frames: Vec<SimpleFrame>,
 }
 
+pub enum Frame {
+    Simple(SimpleFrame), // type 0
+    Group(GroupFrame),   // type !0
+}
+
+impl Frame {
+    /// simple frames of this frame. one for a simple frame,
+    /// all subframes for a group frame
+    pub fn frames(&self) -> &[SimpleFrame] {
+        match self {
+            Frame::Simple(frame) => std::slice::from_ref(frame),
+            Frame::Group(group) => &group.frames,
+        }
+    }
+
+    /// the frame shown at time seconds, see group_index.
+    /// None for an empty group
+    pub fn frame_at(&self, time: f32) -> Option<&SimpleFrame> {
+        match self {
+            Frame::Simple(frame) => Some(frame),
+            Frame::Group(group) => {
+                let idx = group_index(&group.time, time);
+                group.frames.get(idx).or(group.frames.last())
+            }
+        }
+    }
+}
+
+impl Skin {
+    /// index of the image shown at time seconds, for skin_rgba.
+    /// see group_index
+    pub fn image_at(&self, time: f32) -> usize {
+        match self {
+            Skin::Simple(_) => 0,
+            Skin::Group(skin) => group_index(&skin.time, time),
+        }
+    }
+}
+
+/// index of the image of a frame or skin group that is shown at time
+/// seconds. like quake, times are cumulative: time[i] is the end of
+/// image i and the last one the length of the cycle, which repeats.
+/// add Model::syncbase to time for models with random synctype
+pub fn group_index(times: &[f32], time: f32) -> usize {
+    let full = match times.last() {
+        Some(full) if *full > 0.0 && full.is_finite() && time.is_finite() => *full,
+        _ => return 0,
+    };
+    let target = time.rem_euclid(full);
+    for i in 0..times.len() - 1 {
+        if times[i] > target {
+            return i;
+        }
+    }
+    times.len() - 1
+}
+
 pub struct Model {
     pub header: Header,
     pub skins: Vec<Skin>,
@@ -104,58 +168,122 @@ pub struct Model {
 }
 
 impl Model {
-    fn read_header(reader: &mut dyn Read) -> Result<Header> {
-        let header = {
-            let mut buf = [0; std::mem::size_of::<Header>()];
-            reader
-                .read_exact(&mut buf)
-                .map_err(|e| Error::io(e, "failed to read header"))?;
-            let header: Header = unsafe { std::mem::transmute(buf) };
-            header
-        };
+    fn read_i32(reader: &mut dyn Read, msg: &str) -> Result<i32> {
+        reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_f32(reader: &mut dyn Read, msg: &str) -> Result<f32> {
+        reader
+            .read_f32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_vec3(reader: &mut dyn Read, msg: &str) -> Result<vec3_t> {
+        let x = Self::read_f32(reader, msg)?;
+        let y = Self::read_f32(reader, msg)?;
+        let z = Self::read_f32(reader, msg)?;
+        Ok([x, y, z])
+    }
 
-        if header.ident != HEADER_IDENT {
-            return Err(Error::ident(header.ident, HEADER_IDENT));
+    fn read_header(reader: &mut dyn Read) -> Result<Header> {
+        let ident = Self::read_i32(reader, "failed to read 'ident'.")?;
+        if ident != HEADER_IDENT {
+            return Err(Error::ident(ident, HEADER_IDENT));
         }
 
-        if header.version != HEADER_VERSION {
-            return Err(Error::version(header.version, HEADER_VERSION));
+        let version = Self::read_i32(reader, "failed to read 'version'.")?;
+        if version != HEADER_VERSION {
+            return Err(Error::version(version, HEADER_VERSION));
         }
+
+        let header = Header {
+            ident: ident,
+            version: version,
+            scale: Self::read_vec3(reader, "failed to read 'scale'.")?,
+            translate: Self::read_vec3(reader, "failed to read 'translate'.")?,
+            boundigradius: Self::read_f32(reader, "failed to read 'boundigradius'.")?,
+            eyeposition: Self::read_vec3(reader, "failed to read 'eyeposition'.")?,
+
+            num_skins: Self::read_i32(reader, "failed to read 'num_skins'.")?,
+            skin_width: Self::read_i32(reader, "failed to read 'skin_width'.")?,
+            skin_height: Self::read_i32(reader, "failed to read 'skin_height'.")?,
+
+            num_verices: Self::read_i32(reader, "failed to read 'num_verices'.")?,
+            num_faces: Self::read_i32(reader, "failed to read 'num_faces'.")?,
+            num_frames: Self::read_i32(reader, "failed to read 'num_frames'.")?,
+
+            synctype: Self::read_i32(reader, "failed to read 'synctype'.")?,
+            flags: Self::read_i32(reader, "failed to read 'flags'.")?,
+            size: Self::read_f32(reader, "failed to read 'size'.")?,
+        };
         Ok(header)
     }
 
-    fn read_skins(reader: &mut dyn Read, header: &Header) -> Result<Vec<Skin>> {
-        let mut skins = Vec::<Skin>::new();
-        let skin_width_x_height = (header.skin_width * header.skin_height) as usize;
-        for _ in 0..header.num_skins {
-            let mut data = Vec::with_capacity(skin_width_x_height);
-            unsafe {
-                data.set_len(skin_width_x_height);
-            }
+    fn read_skins(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Skin>> {
+        let num_skins = budget.alloc("num_skins", header.num_skins as i64, size_of::<Skin>())?;
+        let mut skins = Vec::<Skin>::with_capacity(num_skins);
+        for _ in 0..num_skins {
             let group = reader
                 .read_i32::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read skin group"))?;
 
-            if group != 0 {
-                return Err(Error::unsupported("skin groups are not supported."));
-            }
+            let skin = if group == 0 {
+                let len = budget.skin(1, header.skin_width, header.skin_height)?;
+                let mut data = vec![0; len];
+                reader
+                    .read_exact(&mut data)
+                    .map_err(|e| Error::io(e, "failed to read skin data"))?;
 
-            reader
-                .read_exact(&mut data)
-                .map_err(|e| Error::io(e, "failed to read skin data"))?;
+                Skin::Simple(SimpleSkin { data: data })
+            } else {
+                let nb = reader
+                    .read_i32::<LittleEndian>()
+                    .map_err(|e| Error::io(e, "failed to read skin group nb"))?;
+
+                let num_times = budget.alloc("skin group nb", nb as i64, size_of::<f32>())?;
+                let mut time = Vec::<f32>::with_capacity(num_times);
+                for _ in 0..num_times {
+                    let t = reader
+                        .read_f32::<LittleEndian>()
+                        .map_err(|e| Error::io(e, "failed to read skin group time"))?;
+                    time.push(t);
+                }
 
-            let skin = Skin {
-                data: data,
-                group: group,
+                let len = budget.skin(nb as i64, header.skin_width, header.skin_height)?;
+                let mut data = vec![0; len];
+                reader
+                    .read_exact(&mut data)
+                    .map_err(|e| Error::io(e, "failed to read skin group data"))?;
+
+                Skin::Group(GroupSkin {
+                    nb: nb,
+                    time: time,
+                    data: data,
+                })
             };
             skins.push(skin);
         }
         Ok(skins)
     }
 
-    fn read_texcoords(reader: &mut dyn Read, header: &Header) -> Result<Vec<TexCoord>> {
-        let mut texcoords = Vec::<TexCoord>::with_capacity(header.num_verices as usize);
-        for _ in 0..header.num_verices {
+    fn read_texcoords(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<TexCoord>> {
+        let num_texcoords = budget.alloc(
+            "num_verices",
+            header.num_verices as i64,
+            size_of::<TexCoord>(),
+        )?;
+        let mut texcoords = Vec::<TexCoord>::with_capacity(num_texcoords);
+        for _ in 0..num_texcoords {
             let onseam = reader
                 .read_i32::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read texcoord"))?;
@@ -175,9 +303,15 @@ impl Model {
         Ok(texcoords)
     }
 
-    fn read_triangles(reader: &mut dyn Read, header: &Header) -> Result<Vec<Triangle>> {
-        let mut triangles = Vec::<Triangle>::with_capacity(header.num_faces as usize);
-        for _ in 0..header.num_faces {
+    fn read_triangles(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Triangle>> {
+        let num_faces =
+            budget.alloc("num_faces", header.num_faces as i64, size_of::<Triangle>())?;
+        let mut triangles = Vec::<Triangle>::with_capacity(num_faces);
+        for _ in 0..num_faces {
             let facefront = reader
                 .read_i32::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read triangle"))?;
@@ -199,75 +333,91 @@ impl Model {
         Ok(triangles)
     }
 
-    fn read_frames(reader: &mut dyn Read, header: &Header) -> Result<Vec<Frame>> {
-        let mut frames = Vec::<Frame>::with_capacity(header.num_frames as usize);
+    fn read_vertex(reader: &mut dyn Read, msg: &str) -> Result<Vertex> {
+        let mut v: [u8; 3] = [0; 3];
+        reader.read_exact(&mut v).map_err(|e| Error::io(e, msg))?;
+        let normal_index = reader.read_u8().map_err(|e| Error::io(e, msg))?;
+        Ok(Vertex {
+            v: v,
+            normal_idx: normal_index,
+        })
+    }
+
+    fn read_simple_frame(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<SimpleFrame> {
         let mut buf: [u8; 16] = [0; 16];
-        for _ in 0..header.num_frames {
+        let bboxmin = Self::read_vertex(reader, "failed to read bbox min")?;
+        let bboxmax = Self::read_vertex(reader, "failed to read bbox max")?;
+
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| Error::io(e, "failed to read frame name."))?;
+        let name =
+            to_utf8(&buf).map_err(|e| Error::utf8(e, "failed to covert frame name to utf8."))?;
+
+        let num_verts = budget.alloc(
+            "num_verices",
+            header.num_verices as i64,
+            size_of::<Vertex>(),
+        )?;
+        let mut verts = Vec::<Vertex>::with_capacity(num_verts);
+        for _ in 0..num_verts {
+            let vertex = Self::read_vertex(reader, "failed to read vertex")?;
+            verts.push(vertex);
+        }
+        Ok(SimpleFrame {
+            bboxmin: bboxmin,
+            bboxmax: bboxmax,
+            name: name,
+            verts: verts,
+        })
+    }
+
+    fn read_frames(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Frame>> {
+        let num_frames = budget.frames(header.num_frames as i64, size_of::<Frame>())?;
+        let mut frames = Vec::<Frame>::with_capacity(num_frames);
+        for _ in 0..num_frames {
             let type_ = reader
                 .read_i32::<LittleEndian>()
                 .map_err(|e| Error::io(e, "failed to read frame type"))?;
 
-            if type_ != 0 {
-                return Err(Error::unsupported("group frames are not supported."));
-            }
-            let bboxmin = {
-                let mut v: [u8; 3] = [0; 3];
-                reader
-                    .read_exact(&mut v)
-                    .map_err(|e| Error::io(e, "failed to read bbox min"))?;
-                let normal_index = reader
-                    .read_u8()
-                    .map_err(|e| Error::io(e, "failed to read bbox min"))?;
-                Vertex {
-                    v: v,
-                    normal_idx: normal_index,
+            let frame = if type_ == 0 {
+                Frame::Simple(Self::read_simple_frame(reader, header, budget)?)
+            } else {
+                let nb = reader
+                    .read_i32::<LittleEndian>()
+                    .map_err(|e| Error::io(e, "failed to read frame group nb"))?;
+                let min = Self::read_vertex(reader, "failed to read frame group min")?;
+                let max = Self::read_vertex(reader, "failed to read frame group max")?;
+
+                let num_times = budget.alloc("frame group nb", nb as i64, size_of::<f32>())?;
+                let mut time = Vec::<f32>::with_capacity(num_times);
+                for _ in 0..num_times {
+                    let t = reader
+                        .read_f32::<LittleEndian>()
+                        .map_err(|e| Error::io(e, "failed to read frame group time"))?;
+                    time.push(t);
                 }
-            };
 
-            let bboxmax = {
-                let mut v: [u8; 3] = [0; 3];
-                reader
-                    .read_exact(&mut v)
-                    .map_err(|e| Error::io(e, "failed to read bbox max"))?;
-                let normal_index = reader
-                    .read_u8()
-                    .map_err(|e| Error::io(e, "failed to read bbox max"))?;
-                Vertex {
-                    v: v,
-                    normal_idx: normal_index,
+                let nb = budget.frames(nb as i64, size_of::<SimpleFrame>())?;
+                let mut group_frames = Vec::<SimpleFrame>::with_capacity(nb);
+                for _ in 0..nb {
+                    group_frames.push(Self::read_simple_frame(reader, header, budget)?);
                 }
-            };
 
-            reader
-                .read_exact(&mut buf)
-                .map_err(|e| Error::io(e, "failed to read frame name."))?;
-            let name = to_utf8(&buf)
-                .map_err(|e| Error::utf8(e, "failed to covert frame name to utf8."))?;
-
-            let mut verts = Vec::<Vertex>::with_capacity(header.num_verices as usize);
-            for _ in 0..header.num_verices {
-                let mut v: [u8; 3] = [0; 3];
-                reader
-                    .read_exact(&mut v)
-                    .map_err(|e| Error::io(e, "failed to read vertex"))?;
-                let normal_index = reader
-                    .read_u8()
-                    .map_err(|e| Error::io(e, "failed to read vertex"))?;
-                let vertex = Vertex {
-                    v: v,
-                    normal_idx: normal_index,
-                };
-                verts.push(vertex);
-            }
-            let simple_frame = SimpleFrame {
-                bboxmin: bboxmin,
-                bboxmax: bboxmax,
-                name: name,
-                verts: verts,
-            };
-            let frame = Frame {
-                type_: type_,
-                frame: simple_frame,
+                Frame::Group(GroupFrame {
+                    min: min,
+                    max: max,
+                    time: time,
+                    frames: group_frames,
+                })
             };
             frames.push(frame);
         }
@@ -275,20 +425,331 @@ impl Model {
     }
 
     pub fn from_reader(reader: &mut dyn Read) -> Result<Self> {
-        let header = Self::read_header(reader)?;
-
-        let skins = Self::read_skins(reader, &header)?;
-        let texcoords = Self::read_texcoords(reader, &header)?;
-        let triangles = Self::read_triangles(reader, &header)?;
-        let frames = Self::read_frames(reader, &header)?;
+        Self::from_reader_with(reader, &LoadOptions::default())
+    }
 
-        Ok(Model {
+    pub fn from_reader_with(reader: &mut dyn Read, options: &LoadOptions) -> Result<Self> {
+        let mut reader = Tracker::new(reader);
+        let mut budget = Budget::new(&options.limits);
+        let header =
+            Self::read_header(&mut reader).map_err(|e| e.at(Section::Header, reader.last))?;
+
+        let skins = Self::read_skins(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Skins, reader.last))?;
+        let texcoords = Self::read_texcoords(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Texcoords, reader.last))?;
+        let triangles = Self::read_triangles(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Triangles, reader.last))?;
+        let frames = Self::read_frames(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Frames, reader.last))?;
+
+        let model = Model {
             header: header,
             skins: skins,
             texcoords: texcoords,
             triangles: triangles,
             frames: frames,
-        })
+        };
+        if options.strict {
+            Error::check(model.validate())?;
+        }
+        Ok(model)
+    }
+
+    /// checks counts, indices and sizes. a model without issues
+    /// can be converted without panics. frame indices are those
+    /// of AliasModel, except for EmptyGroup which indexes frames
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::<Issue>::new();
+        let num_vertices = self.texcoords.len();
+        check_count(&mut issues, Item::Vertices, num_vertices, MAX_VERTICES);
+        check_count(
+            &mut issues,
+            Item::Triangles,
+            self.triangles.len(),
+            MAX_TRIANGLES,
+        );
+        check_count(&mut issues, Item::Frames, self.frames.len(), MAX_FRAMES);
+
+        let skin_size =
+            self.header.skin_width.max(0) as usize * self.header.skin_height.max(0) as usize;
+        for (i, skin) in self.skins.iter().enumerate() {
+            let (len, expected) = match skin {
+                Skin::Simple(skin) => (skin.data.len(), skin_size),
+                Skin::Group(skin) => (skin.data.len(), skin.nb.max(0) as usize * skin_size),
+            };
+            if len != expected {
+                issues.push(Issue::SkinSize {
+                    skin: i,
+                    len: len,
+                    expected: expected,
+                });
+            }
+        }
+
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            for &index in &triangle.vertex {
+                if !in_range(index as i64, num_vertices) {
+                    issues.push(Issue::VertexIndex {
+                        triangle: i,
+                        index: index as i64,
+                        len: num_vertices,
+                    });
+                }
+            }
+        }
+
+        if self.frames.is_empty() {
+            issues.push(Issue::NoFrames);
+        }
+
+        let mut frame_idx = 0;
+        for (i, frame) in self.frames.iter().enumerate() {
+            if let Frame::Group(group) = frame {
+                if group.frames.is_empty() {
+                    issues.push(Issue::EmptyGroup { frame: i });
+                }
+                if group.time.len() != group.frames.len() {
+                    issues.push(Issue::GroupTimes {
+                        frame: i,
+                        count: group.time.len(),
+                        expected: group.frames.len(),
+                    });
+                }
+            }
+            for simple_frame in frame.frames() {
+                if simple_frame.verts.len() != num_vertices {
+                    issues.push(Issue::VertexCount {
+                        frame: frame_idx,
+                        count: simple_frame.verts.len(),
+                        expected: num_vertices,
+                    });
+                }
+                for (v, vertex) in simple_frame.verts.iter().enumerate() {
+                    check_normal(&mut issues, frame_idx, v, vertex.normal_idx);
+                }
+                frame_idx += 1;
+            }
+        }
+
+        issues
+    }
+
+    fn write_i32(writer: &mut dyn Write, value: i32, msg: &str) -> Result<()> {
+        writer
+            .write_i32::<LittleEndian>(value)
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn write_f32(writer: &mut dyn Write, value: f32, msg: &str) -> Result<()> {
+        writer
+            .write_f32::<LittleEndian>(value)
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn write_vec3(writer: &mut dyn Write, value: &vec3_t, msg: &str) -> Result<()> {
+        for v in value.iter() {
+            Self::write_f32(writer, *v, msg)?;
+        }
+        Ok(())
+    }
+
+    fn write_vertex(writer: &mut dyn Write, vertex: &Vertex, msg: &str) -> Result<()> {
+        writer.write_all(&vertex.v).map_err(|e| Error::io(e, msg))?;
+        writer
+            .write_u8(vertex.normal_idx)
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn write_mdl_header(&self, writer: &mut dyn Write) -> Result<()> {
+        let header = &self.header;
+        Self::write_i32(writer, HEADER_IDENT, "failed to write 'ident'.")?;
+        Self::write_i32(writer, HEADER_VERSION, "failed to write 'version'.")?;
+        Self::write_vec3(writer, &header.scale, "failed to write 'scale'.")?;
+        Self::write_vec3(writer, &header.translate, "failed to write 'translate'.")?;
+        Self::write_f32(
+            writer,
+            header.boundigradius,
+            "failed to write 'boundigradius'.",
+        )?;
+        Self::write_vec3(
+            writer,
+            &header.eyeposition,
+            "failed to write 'eyeposition'.",
+        )?;
+
+        let num_skins = self.skins.len() as i32;
+        Self::write_i32(writer, num_skins, "failed to write 'num_skins'.")?;
+        Self::write_i32(writer, header.skin_width, "failed to write 'skin_width'.")?;
+        Self::write_i32(writer, header.skin_height, "failed to write 'skin_height'.")?;
+
+        let num_verices = self.texcoords.len() as i32;
+        Self::write_i32(writer, num_verices, "failed to write 'num_verices'.")?;
+        let num_faces = self.triangles.len() as i32;
+        Self::write_i32(writer, num_faces, "failed to write 'num_faces'.")?;
+        let num_frames = self.frames.len() as i32;
+        Self::write_i32(writer, num_frames, "failed to write 'num_frames'.")?;
+
+        Self::write_i32(writer, header.synctype, "failed to write 'synctype'.")?;
+        Self::write_i32(writer, header.flags, "failed to write 'flags'.")?;
+        Self::write_f32(writer, header.size, "failed to write 'size'.")
+    }
+
+    fn write_mdl_skins(&self, writer: &mut dyn Write) -> Result<()> {
+        for skin in &self.skins {
+            match skin {
+                Skin::Simple(skin) => {
+                    Self::write_i32(writer, 0, "failed to write skin group")?;
+                    writer
+                        .write_all(&skin.data)
+                        .map_err(|e| Error::io(e, "failed to write skin data"))?;
+                }
+                Skin::Group(skin) => {
+                    Self::write_i32(writer, 1, "failed to write skin group")?;
+                    Self::write_i32(
+                        writer,
+                        skin.time.len() as i32,
+                        "failed to write skin group nb",
+                    )?;
+                    for t in &skin.time {
+                        Self::write_f32(writer, *t, "failed to write skin group time")?;
+                    }
+                    writer
+                        .write_all(&skin.data)
+                        .map_err(|e| Error::io(e, "failed to write skin group data"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_mdl_texcoords(&self, writer: &mut dyn Write) -> Result<()> {
+        for texcoord in &self.texcoords {
+            Self::write_i32(writer, texcoord.onseam, "failed to write texcoord")?;
+            Self::write_i32(writer, texcoord.s, "failed to write texcoord")?;
+            Self::write_i32(writer, texcoord.t, "failed to write texcoord")?;
+        }
+        Ok(())
+    }
+
+    fn write_mdl_triangles(&self, writer: &mut dyn Write) -> Result<()> {
+        for triangle in &self.triangles {
+            Self::write_i32(writer, triangle.facefront, "failed to write triangle")?;
+            for v in triangle.vertex.iter() {
+                Self::write_i32(writer, *v, "failed to write triangle")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_simple_frame(writer: &mut dyn Write, frame: &SimpleFrame) -> Result<()> {
+        Self::write_vertex(writer, &frame.bboxmin, "failed to write bbox min")?;
+        Self::write_vertex(writer, &frame.bboxmax, "failed to write bbox max")?;
+
+        let name = frame.name.as_bytes();
+        if name.len() > 16 {
+            return Err(Error::unsupported(&format!(
+                "frame name '{}' is longer than 16 bytes.",
+                frame.name
+            )));
+        }
+        let mut buf: [u8; 16] = [0; 16];
+        buf[..name.len()].copy_from_slice(name);
+        writer
+            .write_all(&buf)
+            .map_err(|e| Error::io(e, "failed to write frame name."))?;
+
+        for vertex in &frame.verts {
+            Self::write_vertex(writer, vertex, "failed to write vertex")?;
+        }
+        Ok(())
+    }
+
+    fn write_mdl_frames(&self, writer: &mut dyn Write) -> Result<()> {
+        for frame in &self.frames {
+            match frame {
+                Frame::Simple(frame) => {
+                    Self::write_i32(writer, 0, "failed to write frame type")?;
+                    Self::write_simple_frame(writer, frame)?;
+                }
+                Frame::Group(group) => {
+                    if group.time.len() != group.frames.len() {
+                        return Err(Error::unsupported(&format!(
+                            "frame group has {} times but {} frames.",
+                            group.time.len(),
+                            group.frames.len()
+                        )));
+                    }
+                    Self::write_i32(writer, 1, "failed to write frame type")?;
+                    let nb = group.frames.len() as i32;
+                    Self::write_i32(writer, nb, "failed to write frame group nb")?;
+                    Self::write_vertex(writer, &group.min, "failed to write frame group min")?;
+                    Self::write_vertex(writer, &group.max, "failed to write frame group max")?;
+                    for t in &group.time {
+                        Self::write_f32(writer, *t, "failed to write frame group time")?;
+                    }
+                    for frame in &group.frames {
+                        Self::write_simple_frame(writer, frame)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the model as mdl. num_skins, num_verices, num_faces
+    /// and num_frames of the header are taken from the vecs,
+    /// the remaining fields are written as they are
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        self.write_mdl_header(writer)?;
+        self.write_mdl_skins(writer)?;
+        self.write_mdl_texcoords(writer)?;
+        self.write_mdl_triangles(writer)?;
+        self.write_mdl_frames(writer)
+    }
+
+    /// decodes image image_idx of skin skin_idx to rgba.
+    /// a simple skin has only image 0, a group skin has nb images.
+    /// see Image::from_indexed for split_fullbright
+    pub fn skin_rgba(
+        &self,
+        skin_idx: usize,
+        image_idx: usize,
+        palette: &Palette,
+        split_fullbright: bool,
+    ) -> Option<(Image, Option<Image>)> {
+        let width = self.header.skin_width.max(0) as u32;
+        let height = self.header.skin_height.max(0) as u32;
+        let width_x_height = width as usize * height as usize;
+
+        let data = match self.skins.get(skin_idx)? {
+            Skin::Simple(skin) if image_idx == 0 => &skin.data[..],
+            Skin::Simple(_) => return None,
+            Skin::Group(skin) => {
+                let start = image_idx.checked_mul(width_x_height)?;
+                let end = start.checked_add(width_x_height)?;
+                skin.data.get(start..end)?
+            }
+        };
+
+        Some(Image::from_indexed(
+            data,
+            width,
+            height,
+            palette,
+            split_fullbright,
+        ))
+    }
+
+    /// offset quake adds to the time of group frames and skins, so
+    /// that models with synctype ST_RAND do not animate in lockstep.
+    /// random is in 0..1 and comes from the caller
+    pub fn syncbase(&self, random: f32) -> f32 {
+        if self.header.synctype == ST_RAND {
+            random
+        } else {
+            0.0
+        }
     }
 
     /// writes model as json to writer
@@ -296,7 +757,7 @@ impl Model {
     pub fn write_json(&self, writer: &mut dyn Write) -> std::result::Result<(), std::io::Error> {
         write!(writer, "{{")?;
 
-        self.write_frames(writer)?; // and normals(?)
+        self.write_frames(writer)?; // and normals
         self.write_triangles(writer)?; // and texcoords
 
         write!(writer, "}}")?;
@@ -304,16 +765,18 @@ impl Model {
     }
 
     fn write_frames(&self, writer: &mut dyn Write) -> std::result::Result<(), std::io::Error> {
-        if self.frames.is_empty() {
-            return Ok(());
-        }
+        let mut frames = self.frames.iter().flat_map(|f| f.frames());
+        let first = match frames.next() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
 
         write!(writer, "\n\t\"frames\": [")?;
 
-        self.write_frame(writer, 0)?;
-        for idx in 1..self.frames.len() {
+        self.write_frame(writer, first)?;
+        for frame in frames {
             write!(writer, ",")?;
-            self.write_frame(writer, idx)?;
+            self.write_frame(writer, frame)?;
         }
         write!(writer, "\t],")?;
         Ok(())
@@ -322,34 +785,32 @@ impl Model {
     fn write_frame(
         &self,
         writer: &mut dyn Write,
-        idx: usize,
+        frame: &SimpleFrame,
     ) -> std::result::Result<(), std::io::Error> {
-        let frame = &self.frames[idx];
         let scale = self.header.scale;
         let translate = self.header.translate;
         
         write!(
             writer,
-            "{{\n\t\t\"name\": \"{}\",\n\t\t\"vertices\": [\n",
-            &frame.frame.name
+            "{{\n\t\t\"name\": \"{}\",\n\t\t\"vertices\": [",
+            &frame.name
         )?;
 
-        let vertices = &frame.frame.verts;
-        let x = ((vertices[0].v[0] as f32) * scale[0]) + translate[0];
-        let y = ((vertices[0].v[1] as f32) * scale[1]) + translate[1];
-        let z = ((vertices[0].v[2] as f32) * scale[2]) + translate[2];
-        write!(writer, "\t\t\t{}, {}, {}", x, y, z)?;
-
-        for i in 1..vertices.len() {
+        let vertices = &frame.verts;
+        for i in 0..vertices.len() {
             let vert = &vertices[i];
             let x = ((vert.v[0] as f32) * scale[0]) + translate[0];
             let y = ((vert.v[1] as f32) * scale[1]) + translate[1];
             let z = ((vert.v[2] as f32) * scale[2]) + translate[2];
-            write!(writer, ",\n\t\t\t{}, {}, {}", x, y, z)?;
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t\t{}, {}, {}", sep, x, y, z)?;
+        }
+        write!(writer, "\n\t\t],\n\t\t\"normals\": [")?;
 
-            // let nx = NORMALS[vert.normal_index as usize][0];
-            // let ny = NORMALS[vert.normal_index as usize][1];
-            // let nz = NORMALS[vert.normal_index as usize][2];
+        for i in 0..vertices.len() {
+            let [nx, ny, nz] = decode_normal(vertices[i].normal_idx);
+            let sep = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n\t\t\t{}, {}, {}", sep, nx, ny, nz)?;
         }
         write!(writer, "\n\t\t]\n\t}}")?;
         Ok(())
@@ -359,8 +820,8 @@ impl Model {
         let w = self.header.skin_width as f32;
         let h = self.header.skin_height as f32;
 
-        let mut texcoords_front = vec![(0f32, 0f32); self.header.num_verices as usize];
-        let mut texcoords_back = vec![(0f32, 0f32); self.header.num_verices as usize];
+        let mut texcoords_front = vec![(0f32, 0f32); self.texcoords.len()];
+        let mut texcoords_back = vec![(0f32, 0f32); self.texcoords.len()];
         let mut indices_front = Vec::<i32>::new();
         let mut indices_back = Vec::<i32>::new();
 
@@ -388,15 +849,12 @@ impl Model {
             }
         }
         // write indices front
-        write!(
-            writer,
-            "\n\t\"indices_front\": [\n\t\t{}, {}, {}",
-            indices_front[0], indices_front[1], indices_front[2]
-        )?;
-        for i in 1..(indices_front.len() / 3) {
+        write!(writer, "\n\t\"indices_front\": [")?;
+        for i in 0..(indices_front.len() / 3) {
             write!(
                 writer,
-                ",\n\t\t{}, {}, {}",
+                "{}\n\t\t{}, {}, {}",
+                if i == 0 { "" } else { "," },
                 indices_front[i * 3 + 0],
                 indices_front[i * 3 + 1],
                 indices_front[i * 3 + 2]
@@ -405,15 +863,12 @@ impl Model {
         write!(writer, "\n\t],\n")?;
 
         // back
-        write!(
-            writer,
-            "\t\"indices_back\": [\n\t\t{}, {}, {}",
-            indices_back[0], indices_back[1], indices_back[2]
-        )?;
-        for i in 1..(indices_back.len() / 3) {
+        write!(writer, "\t\"indices_back\": [")?;
+        for i in 0..(indices_back.len() / 3) {
             write!(
                 writer,
-                ",\n\t\t{}, {}, {}",
+                "{}\n\t\t{}, {}, {}",
+                if i == 0 { "" } else { "," },
                 indices_back[i * 3 + 0],
                 indices_back[i * 3 + 1],
                 indices_back[i * 3 + 2]
@@ -422,34 +877,139 @@ impl Model {
         write!(writer, "\n\t],\n")?;
 
         // write texture coordinates front
-        write!(
-            writer,
-            "\t\"texcoords_front\": [\n\t\t{}, {}",
-            texcoords_front[0].0, texcoords_front[0].1
-        )?;
-        for i in 1..texcoords_front.len() {
+        write!(writer, "\t\"texcoords_front\": [")?;
+        for i in 0..texcoords_front.len() {
             write!(
                 writer,
-                ",\n\t\t{}, {}",
-                texcoords_front[i].0, texcoords_front[i].1
+                "{}\n\t\t{}, {}",
+                if i == 0 { "" } else { "," },
+                texcoords_front[i].0,
+                texcoords_front[i].1
             )?;
         }
         write!(writer, "\n\t],\n")?;
 
         // back
-        write!(
-            writer,
-            "\t\"texcoords_back\": [\n\t\t{}, {}",
-            texcoords_back[0].0, texcoords_back[0].1
-        )?;
-        for i in 1..texcoords_back.len() {
+        write!(writer, "\t\"texcoords_back\": [")?;
+        for i in 0..texcoords_back.len() {
             write!(
                 writer,
-                ",\n\t\t{}, {}",
-                texcoords_back[i].0, texcoords_back[i].1
+                "{}\n\t\t{}, {}",
+                if i == 0 { "" } else { "," },
+                texcoords_back[i].0,
+                texcoords_back[i].1
             )?;
         }
         write!(writer, "\n\t]\n")?;
         Ok(())
     }
 }
+
+impl Model {
+    fn simple_frame(&self, frame: usize) -> &SimpleFrame {
+        self.frames
+            .iter()
+            .flat_map(|f| f.frames())
+            .nth(frame)
+            .expect("frame index out of range")
+    }
+}
+
+impl AliasModel for Model {
+    fn num_frames(&self) -> usize {
+        self.frames.iter().map(|f| f.frames().len()).sum()
+    }
+
+    fn frame_name(&self, frame: usize) -> &str {
+        &self.simple_frame(frame).name
+    }
+
+    fn num_vertices(&self) -> usize {
+        self.texcoords.len()
+    }
+
+    fn positions(&self, frame: usize) -> Vec<vec3_t> {
+        let scale = self.header.scale;
+        let translate = self.header.translate;
+        self.simple_frame(frame)
+            .verts
+            .iter()
+            .map(|vertex| {
+                let x = ((vertex.v[0] as f32) * scale[0]) + translate[0];
+                let y = ((vertex.v[1] as f32) * scale[1]) + translate[1];
+                let z = ((vertex.v[2] as f32) * scale[2]) + translate[2];
+                [x, y, z]
+            })
+            .collect()
+    }
+
+    fn normals(&self, frame: usize) -> Vec<vec3_t> {
+        self.simple_frame(frame)
+            .verts
+            .iter()
+            .map(|vertex| decode_normal(vertex.normal_idx))
+            .collect()
+    }
+
+    /// back faces of vertices on the seam use the texcoord
+    /// at index + num_vertices (see texcoords)
+    fn faces(&self) -> Vec<Face> {
+        let num_vertices = self.texcoords.len();
+        self.triangles
+            .iter()
+            .map(|triangle| {
+                let mut face = Face {
+                    vertex: [0; 3],
+                    texcoord: [0; 3],
+                };
+                for i in 0..3 {
+                    let idx = triangle.vertex[i] as usize;
+                    face.vertex[i] = idx;
+                    let back = triangle.facefront == 0 && self.texcoords[idx].onseam > 0;
+                    face.texcoord[i] = if back { idx + num_vertices } else { idx };
+                }
+                face
+            })
+            .collect()
+    }
+
+    /// front texcoords of all vertices, followed by the
+    /// back texcoords (shifted by half the skin width)
+    fn texcoords(&self) -> Vec<(f32, f32)> {
+        let w = self.header.skin_width as f32;
+        let h = self.header.skin_height as f32;
+
+        let front = self.texcoords.iter().map(|st| {
+            let s = (st.s as f32 + 0.5) / w;
+            let t = (st.t as f32 + 0.5) / h;
+            (s, t)
+        });
+        let back = self.texcoords.iter().map(|st| {
+            let s = (st.s as f32 + (w * 0.5f32) + 0.5) / w;
+            let t = (st.t as f32 + 0.5) / h;
+            (s, t)
+        });
+        front.chain(back).collect()
+    }
+
+    /// group skins are represented by their first image
+    fn skins(&self) -> Vec<SkinRef<'_>> {
+        let width = self.header.skin_width.max(0) as u32;
+        let height = self.header.skin_height.max(0) as u32;
+        let width_x_height = width as usize * height as usize;
+        self.skins
+            .iter()
+            .map(|skin| {
+                let data = match skin {
+                    Skin::Simple(skin) => &skin.data[..],
+                    Skin::Group(skin) => &skin.data[..width_x_height.min(skin.data.len())],
+                };
+                SkinRef::Indexed {
+                    width: width,
+                    height: height,
+                    data: data,
+                }
+            })
+            .collect()
+    }
+}
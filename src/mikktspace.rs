@@ -0,0 +1,685 @@
+//! tangent space generation after morten s. mikkelsen's mikktspace, the
+//! reference blender and most engines bake and shade normal maps with.
+//! this is an altered version of the original c code, ported to rust for
+//! triangles only. the welding, the neighbour search and the grouping
+//! follow the original step by step, quirks included, so the tangents
+//! come out the same. the original is distributed under this notice:
+//!
+//! Copyright (C) 2011 by Morten S. Mikkelsen
+//!
+//! This software is provided 'as-is', without any express or implied
+//! warranty.  In no event will the authors be held liable for any damages
+//! arising from the use of this software.
+//!
+//! Permission is granted to anyone to use this software for any purpose,
+//! including commercial applications, and to alter it and redistribute it
+//! freely, subject to the following restrictions:
+//!
+//! 1. The origin of this software must not be misrepresented; you must not
+//!    claim that you wrote the original software. If you use this software
+//!    in a product, an acknowledgment in the product documentation would be
+//!    appreciated but is not required.
+//!
+//! 2. Altered source versions must be plainly marked as such, and must not be
+//!    misrepresented as being the original software.
+//!
+//! 3. This notice may not be removed or altered from any source distribution.
+
+use super::{add, dot, scale, sub, vec3_t};
+use std::cmp::Ordering;
+
+/// the mesh as seen by the algorithm. corners are numbered
+/// face * 4 + corner like in the original, the order of the
+/// numbers decides which neighbours the edge search finds
+struct Geometry<'a> {
+    positions: &'a [vec3_t],
+    normals: &'a [vec3_t],
+    texcoords: &'a [[f32; 2]],
+    triangles: &'a [[usize; 3]],
+}
+
+impl<'a> Geometry<'a> {
+    fn vertex(&self, index: usize) -> usize {
+        self.triangles[index >> 2][index & 3]
+    }
+
+    fn position(&self, index: usize) -> vec3_t {
+        self.positions[self.vertex(index)]
+    }
+
+    fn normal(&self, index: usize) -> vec3_t {
+        self.normals[self.vertex(index)]
+    }
+
+    fn texcoord(&self, index: usize) -> [f32; 2] {
+        self.texcoords[self.vertex(index)]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct TSpace {
+    os: vec3_t,
+    orient: bool,
+}
+
+#[derive(Clone)]
+struct TriInfo {
+    neighbors: [Option<usize>; 3],
+    groups: [Option<usize>; 3],
+    os: vec3_t,
+    ot: vec3_t,
+    /// index of the triangle in the input
+    face: usize,
+    degenerate: bool,
+    /// the texture space of the triangle is unusable, it
+    /// takes the orientation of the first group reaching it
+    group_with_any: bool,
+    orient_preserving: bool,
+}
+
+struct Group {
+    faces: Vec<usize>,
+    vertex: usize,
+    orient_preserving: bool,
+}
+
+/// tangents for every corner of the triangles, three per triangle in the
+/// order of their corners. x, y and z are the tangent, w the sign of the
+/// bitangent: cross(normal, tangent) * w. positions, normals and texcoords
+/// are indexed by the triangles, which need to wind counter clockwise
+pub(crate) fn generate(
+    positions: &[vec3_t],
+    normals: &[vec3_t],
+    texcoords: &[[f32; 2]],
+    triangles: &[[usize; 3]],
+) -> Vec<[f32; 4]> {
+    let geometry = Geometry {
+        positions: positions,
+        normals: normals,
+        texcoords: texcoords,
+        triangles: triangles,
+    };
+    let mut tspaces = vec![
+        TSpace {
+            os: [1.0, 0.0, 0.0],
+            orient: false,
+        };
+        triangles.len() * 3
+    ];
+    gen_tang_space(&geometry, &mut tspaces);
+    tspaces
+        .iter()
+        .map(|ts| {
+            let w = if ts.orient { 1.0 } else { -1.0 };
+            [ts.os[0], ts.os[1], ts.os[2], w]
+        })
+        .collect()
+}
+
+fn gen_tang_space(geometry: &Geometry, tspaces: &mut [TSpace]) {
+    let total = geometry.triangles.len();
+    if total == 0 {
+        return;
+    }
+    // the original takes an angular threshold, 180 degrees by default
+    let thres_cos = 180f32.to_radians().cos();
+
+    let mut tri_list = Vec::with_capacity(total * 3);
+    let mut infos = Vec::with_capacity(total);
+    for f in 0..total {
+        for i in 0..3 {
+            tri_list.push(f << 2 | i);
+        }
+        infos.push(TriInfo {
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: [0.0; 3],
+            ot: [0.0; 3],
+            face: f,
+            degenerate: false,
+            group_with_any: false,
+            orient_preserving: false,
+        });
+    }
+    generate_shared_vertices_index_list(geometry, &mut tri_list);
+
+    let mut degenerate = 0;
+    for t in 0..total {
+        let p0 = geometry.position(tri_list[t * 3 + 0]);
+        let p1 = geometry.position(tri_list[t * 3 + 1]);
+        let p2 = geometry.position(tri_list[t * 3 + 2]);
+        if p0 == p1 || p0 == p2 || p1 == p2 {
+            infos[t].degenerate = true;
+            degenerate += 1;
+        }
+    }
+    let good = total - degenerate;
+    if good == 0 {
+        return;
+    }
+
+    degen_prologue(&mut infos, &mut tri_list, good);
+    init_tri_info(geometry, &mut infos, &tri_list, good);
+    build_neighbors_fast(&mut infos, &tri_list, good);
+    let groups = build_4_rule_groups(&mut infos, &tri_list, good);
+    generate_tspaces(geometry, tspaces, &infos, &groups, &tri_list, thres_cos);
+    degen_epilogue(tspaces, &infos, &tri_list, good);
+}
+
+/// welds corners with the same position, normal and texcoord. the
+/// corners are bucketed along the longest axis of the bounding box
+fn generate_shared_vertices_index_list(geometry: &Geometry, tri_list: &mut [usize]) {
+    let mut min = geometry.position(0);
+    let mut max = min;
+    for i in 1..tri_list.len() {
+        let p = geometry.position(tri_list[i]);
+        for c in 0..3 {
+            if min[c] > p[c] {
+                min[c] = p[c];
+            } else if max[c] < p[c] {
+                max[c] = p[c];
+            }
+        }
+    }
+    let dim = sub(max, min);
+    let mut channel = 0;
+    if dim[1] > dim[0] && dim[1] > dim[2] {
+        channel = 1;
+    } else if dim[2] > dim[0] {
+        channel = 2;
+    }
+    let (cell_min, cell_max) = (min[channel], max[channel]);
+
+    let mut counts = vec![0usize; CELLS];
+    for i in 0..tri_list.len() {
+        let value = geometry.position(tri_list[i])[channel];
+        counts[find_grid_cell(cell_min, cell_max, value)] += 1;
+    }
+    let mut offsets = vec![0usize; CELLS];
+    for k in 1..CELLS {
+        offsets[k] = offsets[k - 1] + counts[k - 1];
+    }
+    let mut table = vec![0usize; tri_list.len()];
+    let mut filled = vec![0usize; CELLS];
+    for i in 0..tri_list.len() {
+        let value = geometry.position(tri_list[i])[channel];
+        let cell = find_grid_cell(cell_min, cell_max, value);
+        table[offsets[cell] + filled[cell]] = i;
+        filled[cell] += 1;
+    }
+
+    for k in 0..CELLS {
+        if counts[k] < 2 {
+            continue;
+        }
+        let mut verts: Vec<(vec3_t, usize)> = table[offsets[k]..offsets[k] + counts[k]]
+            .iter()
+            .map(|&i| (geometry.position(tri_list[i]), i))
+            .collect();
+        let right = verts.len() - 1;
+        merge_verts_fast(geometry, tri_list, &mut verts, 0, right);
+    }
+}
+
+const CELLS: usize = 2048;
+
+fn find_grid_cell(min: f32, max: f32, value: f32) -> usize {
+    let index = (CELLS as f32 * ((value - min) / (max - min))) as isize;
+    if index < 0 {
+        0
+    } else if index < CELLS as isize {
+        index as usize
+    } else {
+        CELLS - 1
+    }
+}
+
+/// splits the bucket at the middle of its longest axis until
+/// it can't be split anymore, then welds the equal corners
+fn merge_verts_fast(
+    geometry: &Geometry,
+    tri_list: &mut [usize],
+    verts: &mut [(vec3_t, usize)],
+    left: usize,
+    right: usize,
+) {
+    let mut min = verts[left].0;
+    let mut max = min;
+    for l in left + 1..=right {
+        for c in 0..3 {
+            if min[c] > verts[l].0[c] {
+                min[c] = verts[l].0[c];
+            } else if max[c] < verts[l].0[c] {
+                max[c] = verts[l].0[c];
+            }
+        }
+    }
+    let dim = sub(max, min);
+    let mut channel = 0;
+    if dim[1] > dim[0] && dim[1] > dim[2] {
+        channel = 1;
+    } else if dim[2] > dim[0] {
+        channel = 2;
+    }
+    let sep = 0.5 * (max[channel] + min[channel]);
+
+    // the original checks sep >= max || sep <= min, which
+    // never ends the splitting when the bucket has a nan
+    if !(sep < max[channel] && sep > min[channel]) {
+        for l in left..=right {
+            let i = verts[l].1;
+            let index = tri_list[i];
+            let p = geometry.position(index);
+            let n = geometry.normal(index);
+            let t = geometry.texcoord(index);
+            for l2 in left..l {
+                let i2 = verts[l2].1;
+                let index2 = tri_list[i2];
+                if p == geometry.position(index2)
+                    && n == geometry.normal(index2)
+                    && t == geometry.texcoord(index2)
+                {
+                    tri_list[i] = index2;
+                    break;
+                }
+            }
+        }
+        return;
+    }
+
+    let mut l = left as isize;
+    let mut r = right as isize;
+    while l < r {
+        let mut ready_left = false;
+        let mut ready_right = false;
+        while !ready_left && l < r {
+            // not less, nan included
+            ready_left = verts[l as usize].0[channel].partial_cmp(&sep) != Some(Ordering::Less);
+            if !ready_left {
+                l += 1;
+            }
+        }
+        while !ready_right && l < r {
+            ready_right = verts[r as usize].0[channel] < sep;
+            if !ready_right {
+                r -= 1;
+            }
+        }
+        if ready_left && ready_right {
+            verts.swap(l as usize, r as usize);
+            l += 1;
+            r -= 1;
+        }
+    }
+    if l == r {
+        if verts[r as usize].0[channel] < sep {
+            l += 1;
+        } else {
+            r -= 1;
+        }
+    }
+    if (left as isize) < r {
+        merge_verts_fast(geometry, tri_list, verts, left, r as usize);
+    }
+    if l < right as isize {
+        merge_verts_fast(geometry, tri_list, verts, l as usize, right);
+    }
+}
+
+/// moves the degenerate triangles behind the good ones
+fn degen_prologue(infos: &mut [TriInfo], tri_list: &mut [usize], good: usize) {
+    let total = infos.len();
+    let mut next_good = 1;
+    let mut t = 0;
+    while t < good {
+        if !infos[t].degenerate {
+            next_good = next_good.max(t + 2);
+        } else {
+            while next_good < total && infos[next_good].degenerate {
+                next_good += 1;
+            }
+            if next_good >= total {
+                break;
+            }
+            for i in 0..3 {
+                tri_list.swap(t * 3 + i, next_good * 3 + i);
+            }
+            infos.swap(t, next_good);
+            next_good += 1;
+        }
+        t += 1;
+    }
+}
+
+/// the texture space of every good triangle: the directions in which
+/// s and t grow, and whether the texcoords keep the winding
+fn init_tri_info(geometry: &Geometry, infos: &mut [TriInfo], tri_list: &[usize], good: usize) {
+    for f in 0..good {
+        let info = &mut infos[f];
+        info.group_with_any = true;
+
+        let v1 = geometry.position(tri_list[f * 3 + 0]);
+        let v2 = geometry.position(tri_list[f * 3 + 1]);
+        let v3 = geometry.position(tri_list[f * 3 + 2]);
+        let t1 = geometry.texcoord(tri_list[f * 3 + 0]);
+        let t2 = geometry.texcoord(tri_list[f * 3 + 1]);
+        let t3 = geometry.texcoord(tri_list[f * 3 + 2]);
+        let t21x = t2[0] - t1[0];
+        let t21y = t2[1] - t1[1];
+        let t31x = t3[0] - t1[0];
+        let t31y = t3[1] - t1[1];
+        let d1 = sub(v2, v1);
+        let d2 = sub(v3, v1);
+
+        let signed_area = t21x * t31y - t21y * t31x;
+        let os = sub(scale(d1, t31y), scale(d2, t21y));
+        let ot = add(scale(d1, -t31x), scale(d2, t21x));
+        info.orient_preserving = signed_area > 0.0;
+
+        if not_zero(signed_area) {
+            let abs_area = signed_area.abs();
+            let len_os = length(os);
+            let len_ot = length(ot);
+            let sign = if info.orient_preserving { 1.0 } else { -1.0 };
+            if not_zero(len_os) {
+                info.os = scale(os, sign / len_os);
+            }
+            if not_zero(len_ot) {
+                info.ot = scale(ot, sign / len_ot);
+            }
+            if not_zero(len_os / abs_area) && not_zero(len_ot / abs_area) {
+                info.group_with_any = false;
+            }
+        }
+    }
+}
+
+/// finds the neighbour across every edge. an edge is only shared by
+/// two triangles that run along it in opposite directions
+fn build_neighbors_fast(infos: &mut [TriInfo], tri_list: &[usize], good: usize) {
+    let mut edges = Vec::with_capacity(good * 3);
+    for f in 0..good {
+        for i in 0..3 {
+            let i0 = tri_list[f * 3 + i];
+            let i1 = tri_list[f * 3 + (i + 1) % 3];
+            edges.push([i0.min(i1), i0.max(i1), f]);
+        }
+    }
+
+    // sort by i0, then runs of equal i0 by i1 and runs of equal i0
+    // and i1 by face. like the original this skips the last run
+    let seed = 39871946;
+    let len = edges.len();
+    quick_sort_edges(&mut edges, 0, len as isize - 1, 0, seed);
+    for channel in 1..3 {
+        let mut start = 0;
+        for i in 1..len {
+            if edges[start][..channel] != edges[i][..channel] {
+                quick_sort_edges(&mut edges, start as isize, i as isize - 1, channel, seed);
+                start = i;
+            }
+        }
+    }
+
+    for i in 0..len {
+        let [i0, i1, f] = edges[i];
+        let (edge_a, a0, a1) = get_edge(&tri_list[f * 3..f * 3 + 3], i0, i1);
+        if infos[f].neighbors[edge_a].is_some() {
+            continue;
+        }
+        let mut j = i + 1;
+        while j < len && edges[j][0] == i0 && edges[j][1] == i1 {
+            let t = edges[j][2];
+            let (edge_b, b0, b1) = get_edge(&tri_list[t * 3..t * 3 + 3], i0, i1);
+            if a0 == b1 && a1 == b0 && infos[t].neighbors[edge_b].is_none() {
+                infos[f].neighbors[edge_a] = Some(t);
+                infos[t].neighbors[edge_b] = Some(f);
+                break;
+            }
+            j += 1;
+        }
+    }
+}
+
+/// the number of the edge of a triangle between the corners i0 and i1,
+/// edge n runs from corner n to the next one, and its ends in winding order
+fn get_edge(indices: &[usize], i0: usize, i1: usize) -> (usize, usize, usize) {
+    if indices[0] == i0 || indices[0] == i1 {
+        if indices[1] == i0 || indices[1] == i1 {
+            (0, indices[0], indices[1])
+        } else {
+            (2, indices[2], indices[0])
+        }
+    } else {
+        (1, indices[1], indices[2])
+    }
+}
+
+fn quick_sort_edges(
+    edges: &mut [[usize; 3]],
+    left: isize,
+    right: isize,
+    channel: usize,
+    mut seed: u32,
+) {
+    let elems = right - left + 1;
+    if elems < 2 {
+        return;
+    } else if elems == 2 {
+        if edges[left as usize][channel] > edges[right as usize][channel] {
+            edges.swap(left as usize, right as usize);
+        }
+        return;
+    }
+
+    let t = seed & 31;
+    seed = seed.wrapping_add(seed.rotate_left(t)).wrapping_add(3);
+
+    let mut l = left;
+    let mut r = right;
+    let index = (seed % elems as u32) as isize;
+    let mid = edges[(index + l) as usize][channel];
+    loop {
+        while edges[l as usize][channel] < mid {
+            l += 1;
+        }
+        while edges[r as usize][channel] > mid {
+            r -= 1;
+        }
+        if l <= r {
+            edges.swap(l as usize, r as usize);
+            l += 1;
+            r -= 1;
+        }
+        if l > r {
+            break;
+        }
+    }
+    if left < r {
+        quick_sort_edges(edges, left, r, channel, seed);
+    }
+    if l < right {
+        quick_sort_edges(edges, l, right, channel, seed);
+    }
+}
+
+/// groups the corners around every vertex. a group grows from a corner
+/// over the edges of its triangle to the neighbours which share the
+/// vertex and the orientation
+fn build_4_rule_groups(infos: &mut [TriInfo], tri_list: &[usize], good: usize) -> Vec<Group> {
+    let mut groups = Vec::new();
+    for f in 0..good {
+        for i in 0..3 {
+            if infos[f].group_with_any || infos[f].groups[i].is_some() {
+                continue;
+            }
+            let g = groups.len();
+            groups.push(Group {
+                faces: vec![f],
+                vertex: tri_list[f * 3 + i],
+                orient_preserving: infos[f].orient_preserving,
+            });
+            infos[f].groups[i] = Some(g);
+
+            let left = infos[f].neighbors[i];
+            let right = infos[f].neighbors[(i + 2) % 3];
+            assign_recur(infos, tri_list, &mut groups[g], g, [left, right]);
+        }
+    }
+    groups
+}
+
+/// adds the neighbours to the group, and their neighbours in turn.
+/// the original recurses, the stack keeps the order of its calls
+fn assign_recur(
+    infos: &mut [TriInfo],
+    tri_list: &[usize],
+    group: &mut Group,
+    g: usize,
+    neighbors: [Option<usize>; 2],
+) {
+    let mut stack: Vec<usize> = neighbors.iter().rev().flatten().copied().collect();
+    while let Some(tri) = stack.pop() {
+        let i = match (0..3).find(|&i| tri_list[tri * 3 + i] == group.vertex) {
+            Some(i) => i,
+            None => continue,
+        };
+        let info = &mut infos[tri];
+        if info.groups[i].is_some() {
+            continue;
+        }
+        if info.group_with_any && info.groups == [None; 3] {
+            info.orient_preserving = group.orient_preserving;
+        }
+        if info.orient_preserving != group.orient_preserving {
+            continue;
+        }
+        group.faces.push(tri);
+        info.groups[i] = Some(g);
+
+        // left is visited first
+        if let Some(right) = info.neighbors[(i + 2) % 3] {
+            stack.push(right);
+        }
+        if let Some(left) = info.neighbors[i] {
+            stack.push(left);
+        }
+    }
+}
+
+/// the tangent space of every corner, averaged over the faces of its
+/// group whose tangents point roughly the same way
+fn generate_tspaces(
+    geometry: &Geometry,
+    tspaces: &mut [TSpace],
+    infos: &[TriInfo],
+    groups: &[Group],
+    tri_list: &[usize],
+    thres_cos: f32,
+) {
+    for g in 0..groups.len() {
+        let group = &groups[g];
+        let mut unique: Vec<(Vec<usize>, vec3_t)> = Vec::new();
+        for &f in &group.faces {
+            let index = (0..3).find(|&i| infos[f].groups[i] == Some(g)).unwrap_or(0);
+            let n = geometry.normal(tri_list[f * 3 + index]);
+            let os = project(infos[f].os, n);
+            let ot = project(infos[f].ot, n);
+
+            let mut members = Vec::new();
+            for &t in &group.faces {
+                let os2 = project(infos[t].os, n);
+                let ot2 = project(infos[t].ot, n);
+                let any = infos[f].group_with_any || infos[t].group_with_any;
+                let same_face = infos[f].face == infos[t].face;
+                if any || same_face || (dot(os, os2) > thres_cos && dot(ot, ot2) > thres_cos) {
+                    members.push(t);
+                }
+            }
+            members.sort_unstable();
+
+            let os = match unique.iter().find(|(m, _)| *m == members) {
+                Some(&(_, os)) => os,
+                None => {
+                    let os = eval_tspace(geometry, infos, tri_list, &members, group.vertex);
+                    unique.push((members, os));
+                    os
+                }
+            };
+            tspaces[infos[f].face * 3 + index] = TSpace {
+                os: os,
+                orient: group.orient_preserving,
+            };
+        }
+    }
+}
+
+/// the tangent of a vertex: the tangents of the faces projected onto
+/// the plane of the normal, weighted by the angle of the face there
+fn eval_tspace(
+    geometry: &Geometry,
+    infos: &[TriInfo],
+    tri_list: &[usize],
+    faces: &[usize],
+    vertex: usize,
+) -> vec3_t {
+    let mut res = [0.0; 3];
+    for &f in faces {
+        if infos[f].group_with_any {
+            continue;
+        }
+        let i = (0..3).find(|&i| tri_list[f * 3 + i] == vertex).unwrap_or(0);
+        let n = geometry.normal(tri_list[f * 3 + i]);
+        let os = project(infos[f].os, n);
+
+        let p0 = geometry.position(tri_list[f * 3 + (i + 2) % 3]);
+        let p1 = geometry.position(tri_list[f * 3 + i]);
+        let p2 = geometry.position(tri_list[f * 3 + (i + 1) % 3]);
+        let v1 = project(sub(p0, p1), n);
+        let v2 = project(sub(p2, p1), n);
+        let cos = dot(v1, v2).clamp(-1.0, 1.0);
+        let angle = (cos as f64).acos() as f32;
+        res = add(res, scale(os, angle));
+    }
+    normalize_not_zero(res)
+}
+
+/// degenerate triangles take the tangent space of the
+/// first good corner that was welded with theirs
+fn degen_epilogue(tspaces: &mut [TSpace], infos: &[TriInfo], tri_list: &[usize], good: usize) {
+    for t in good..infos.len() {
+        for i in 0..3 {
+            let index = tri_list[t * 3 + i];
+            if let Some(j) = tri_list[..good * 3]
+                .iter()
+                .position(|&index2| index2 == index)
+            {
+                let src = infos[j / 3].face * 3 + j % 3;
+                tspaces[infos[t].face * 3 + i] = tspaces[src];
+            }
+        }
+    }
+}
+
+fn not_zero(x: f32) -> bool {
+    x.abs() > 1.175_494_4e-38
+}
+
+fn length(a: vec3_t) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// a made unit length, unless it is zero. unlike super::normalize
+/// this leaves tiny vectors alone, as the original does
+fn normalize_not_zero(a: vec3_t) -> vec3_t {
+    if not_zero(a[0]) || not_zero(a[1]) || not_zero(a[2]) {
+        scale(a, 1.0 / length(a))
+    } else {
+        a
+    }
+}
+
+/// a projected onto the plane of the normal n
+fn project(a: vec3_t, n: vec3_t) -> vec3_t {
+    normalize_not_zero(sub(a, scale(n, dot(n, a))))
+}
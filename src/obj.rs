@@ -0,0 +1,118 @@
+use super::flat_model::FlatModel;
+use super::image::Image;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const MATERIAL_NAME: &str = "skin";
+
+/// which frames export writes
+pub enum Frames {
+    One(usize),
+    All, // one file per frame
+}
+
+/// writes frame frame_idx as wavefront obj. faces reference
+/// position, texcoord and normal with the same index. normals
+/// are left out if the frame does not have one per vertex.
+/// obj texcoords start at the bottom, so t is flipped and
+/// faces are wound counter clockwise, so b and c are swapped
+pub fn write_obj(
+    model: &FlatModel,
+    frame_idx: usize,
+    mtl_name: Option<&str>,
+    writer: &mut dyn Write,
+) -> Result<(), std::io::Error> {
+    let vertices = model.vertices.get(frame_idx).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "frame out of range")
+    })?;
+    let normals = model
+        .normals
+        .get(frame_idx)
+        .filter(|normals| normals.len() == vertices.len());
+
+    if let Some(mtl_name) = mtl_name {
+        write!(writer, "mtllib {}\n", mtl_name)?;
+    }
+
+    for v in vertices {
+        write!(writer, "v {} {} {}\n", v[0], v[1], v[2])?;
+    }
+    for (s, t) in &model.texcoords {
+        write!(writer, "vt {} {}\n", s, 1.0 - t)?;
+    }
+    if let Some(normals) = normals {
+        for n in normals {
+            write!(writer, "vn {} {} {}\n", n[0], n[1], n[2])?;
+        }
+    }
+
+    if mtl_name.is_some() {
+        write!(writer, "usemtl {}\n", MATERIAL_NAME)?;
+    }
+    for (a, b, c) in &model.indices {
+        // obj indices start at 1
+        let (a, b, c) = (a + 1, b + 1, c + 1);
+        if normals.is_some() {
+            write!(writer, "f {0}/{0}/{0} {2}/{2}/{2} {1}/{1}/{1}\n", a, b, c)?;
+        } else {
+            write!(writer, "f {0}/{0} {2}/{2} {1}/{1}\n", a, b, c)?;
+        }
+    }
+    Ok(())
+}
+
+/// writes a material library with a single material
+/// that uses texture as diffuse map
+pub fn write_mtl(texture: Option<&str>, writer: &mut dyn Write) -> Result<(), std::io::Error> {
+    write!(writer, "newmtl {}\n", MATERIAL_NAME)?;
+    write!(writer, "Ka 1 1 1\nKd 1 1 1\nKs 0 0 0\nd 1\nillum 1\n")?;
+    if let Some(texture) = texture {
+        write!(writer, "map_Kd {}\n", texture)?;
+    }
+    Ok(())
+}
+
+/// writes name.obj (or name_000.obj, name_001.obj, ... for Frames::All),
+/// name.mtl and, if a skin is given, name.png to dir
+pub fn export(
+    model: &FlatModel,
+    frames: Frames,
+    skin: Option<&Image>,
+    dir: &Path,
+    name: &str,
+) -> Result<(), std::io::Error> {
+    let texture = match skin {
+        Some(skin) => {
+            let texture = format!("{}.png", name);
+            let mut writer = BufWriter::new(File::create(dir.join(&texture))?);
+            skin.write_png(&mut writer)?;
+            writer.flush()?;
+            Some(texture)
+        }
+        None => None,
+    };
+
+    let mtl_name = format!("{}.mtl", name);
+    let mut writer = BufWriter::new(File::create(dir.join(&mtl_name))?);
+    write_mtl(texture.as_deref(), &mut writer)?;
+    writer.flush()?;
+
+    match frames {
+        Frames::One(frame_idx) => {
+            let path = dir.join(format!("{}.obj", name));
+            let mut writer = BufWriter::new(File::create(path)?);
+            write_obj(model, frame_idx, Some(&mtl_name), &mut writer)?;
+            writer.flush()?;
+        }
+        Frames::All => {
+            for frame_idx in 0..model.vertices.len() {
+                let path = dir.join(format!("{}_{:03}.obj", name, frame_idx));
+                let mut writer = BufWriter::new(File::create(path)?);
+                write_obj(model, frame_idx, Some(&mtl_name), &mut writer)?;
+                writer.flush()?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use super::{Error, Result, Section, Tracker, COLORMAP};
+use std::io::Read;
+
+/// 256 rgb colors. palette indices of skins
+/// and textures are resolved with it
+pub struct Palette {
+    pub colors: [[u8; 3]; 256],
+}
+
+impl Default for Palette {
+    /// the quake palette super::COLORMAP
+    fn default() -> Self {
+        Palette { colors: COLORMAP }
+    }
+}
+
+impl Palette {
+    /// reads a palette.lmp: 256 rgb triplets, 768 bytes
+    pub fn from_reader(reader: &mut dyn Read) -> Result<Self> {
+        let mut reader = Tracker::new(reader);
+        let mut colors = [[0u8; 3]; 256];
+        for color in colors.iter_mut() {
+            reader.read_exact(color).map_err(|e| {
+                Error::io(e, "failed to read palette").at(Section::Palette, reader.last)
+            })?;
+        }
+        Ok(Palette { colors: colors })
+    }
+
+    pub fn color(&self, idx: u8) -> [u8; 3] {
+        self.colors[idx as usize]
+    }
+}
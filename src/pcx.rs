@@ -0,0 +1,217 @@
+extern crate byteorder;
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use super::image::Image;
+use super::md2;
+use super::palette::Palette;
+use super::{Budget, Error, Limits, Result, Section, Tracker};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const HEADER_MANUFACTURER: u8 = 10;
+pub const PALETTE_MARKER: u8 = 12;
+
+#[derive(Debug)]
+pub struct Header {
+    pub manufacturer: u8,   // 10
+    pub version: u8,        // 5
+    pub encoding: u8,       // 1 - rle
+    pub bits_per_pixel: u8, // 8
+    pub xmin: u16,
+    pub ymin: u16,
+    pub xmax: u16,
+    pub ymax: u16,
+    pub hres: u16,
+    pub vres: u16,
+    // 48 bytes 16 color palette. unused
+    pub reserved: u8,
+    pub color_planes: u8, // 1
+    pub bytes_per_line: u16,
+    pub palette_type: u16,
+    // 58 bytes filler
+}
+
+/// 8 bit paletted pcx image. the palette
+/// is stored after the image data
+pub struct Pcx {
+    pub header: Header,
+    pub palette: Palette,
+    pub data: Vec<u8>, // width * height palette indices
+}
+
+impl Pcx {
+    fn read_u8(reader: &mut dyn Read, msg: &str) -> Result<u8> {
+        reader.read_u8().map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_u16(reader: &mut dyn Read, msg: &str) -> Result<u16> {
+        reader
+            .read_u16::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_header(reader: &mut dyn Read) -> Result<Header> {
+        let manufacturer = Self::read_u8(reader, "failed to read 'manufacturer'.")?;
+        if manufacturer != HEADER_MANUFACTURER {
+            return Err(Error::ident(
+                manufacturer as i32,
+                HEADER_MANUFACTURER as i32,
+            ));
+        }
+
+        let version = Self::read_u8(reader, "failed to read 'version'.")?;
+        let encoding = Self::read_u8(reader, "failed to read 'encoding'.")?;
+        let bits_per_pixel = Self::read_u8(reader, "failed to read 'bits_per_pixel'.")?;
+        let xmin = Self::read_u16(reader, "failed to read 'xmin'.")?;
+        let ymin = Self::read_u16(reader, "failed to read 'ymin'.")?;
+        let xmax = Self::read_u16(reader, "failed to read 'xmax'.")?;
+        let ymax = Self::read_u16(reader, "failed to read 'ymax'.")?;
+        let hres = Self::read_u16(reader, "failed to read 'hres'.")?;
+        let vres = Self::read_u16(reader, "failed to read 'vres'.")?;
+
+        let mut ega_palette = [0; 48];
+        reader
+            .read_exact(&mut ega_palette)
+            .map_err(|e| Error::io(e, "failed to read 16 color palette."))?;
+
+        let reserved = Self::read_u8(reader, "failed to read 'reserved'.")?;
+        let color_planes = Self::read_u8(reader, "failed to read 'color_planes'.")?;
+        let bytes_per_line = Self::read_u16(reader, "failed to read 'bytes_per_line'.")?;
+        let palette_type = Self::read_u16(reader, "failed to read 'palette_type'.")?;
+
+        let mut filler = [0; 58];
+        reader
+            .read_exact(&mut filler)
+            .map_err(|e| Error::io(e, "failed to read filler."))?;
+
+        if encoding != 1 || bits_per_pixel != 8 || color_planes != 1 {
+            return Err(Error::unsupported(
+                "only rle encoded 8 bit pcx with one color plane is supported.",
+            ));
+        }
+
+        if xmax < xmin || ymax < ymin || (bytes_per_line as u32) < (xmax - xmin) as u32 + 1 {
+            return Err(Error::unsupported("invalid pcx dimensions."));
+        }
+
+        Ok(Header {
+            manufacturer: manufacturer,
+            version: version,
+            encoding: encoding,
+            bits_per_pixel: bits_per_pixel,
+            xmin: xmin,
+            ymin: ymin,
+            xmax: xmax,
+            ymax: ymax,
+            hres: hres,
+            vres: vres,
+            reserved: reserved,
+            color_planes: color_planes,
+            bytes_per_line: bytes_per_line,
+            palette_type: palette_type,
+        })
+    }
+
+    /// decodes the rle data line by line. a run may
+    /// continue on the next line, padding bytes at the
+    /// end of a line (bytes_per_line > width) are dropped.
+    /// runs of length 0 (0xc0) produce no pixels
+    fn read_data(reader: &mut dyn Read, header: &Header, budget: &mut Budget) -> Result<Vec<u8>> {
+        let width = (header.xmax - header.xmin) as usize + 1;
+        let height = (header.ymax - header.ymin) as usize + 1;
+        let bytes_per_line = header.bytes_per_line as usize;
+
+        let len = budget.skin(1, width as i32, height as i32)?;
+        let mut data = Vec::<u8>::with_capacity(len);
+        let mut run = 0;
+        let mut value = 0;
+        for _ in 0..height {
+            for x in 0..bytes_per_line {
+                while run == 0 {
+                    let byte = Self::read_u8(reader, "failed to read pcx data.")?;
+                    if byte & 0xc0 == 0xc0 {
+                        run = (byte & 0x3f) as usize;
+                        value = Self::read_u8(reader, "failed to read pcx data.")?;
+                    } else {
+                        run = 1;
+                        value = byte;
+                    }
+                }
+                run -= 1;
+                if x < width {
+                    data.push(value);
+                }
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn read_palette<T: Read + Seek>(reader: &mut T) -> Result<Palette> {
+        reader
+            .seek(SeekFrom::End(-769))
+            .map_err(|e| Error::io(e, "palette seek failed."))?;
+        let marker = Self::read_u8(reader, "failed to read palette marker.")?;
+        if marker != PALETTE_MARKER {
+            return Err(Error::unsupported("pcx has no 256 color palette."));
+        }
+
+        Palette::from_reader(reader)
+    }
+
+    pub fn from_reader<T: Read + Seek>(reader: &mut T) -> Result<Self> {
+        Self::from_reader_with(reader, &Limits::default())
+    }
+
+    /// the image size is checked against limits.max_skin_pixels
+    pub fn from_reader_with<T: Read + Seek>(reader: &mut T, limits: &Limits) -> Result<Self> {
+        let mut reader = Tracker::seekable(reader)?;
+        let mut budget = Budget::new(limits);
+        let header =
+            Self::read_header(&mut reader).map_err(|e| e.at(Section::Header, reader.last))?;
+        let data = Self::read_data(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Pixels, reader.last))?;
+        let palette =
+            Self::read_palette(&mut reader).map_err(|e| e.at(Section::Palette, reader.last))?;
+
+        Ok(Pcx {
+            header: header,
+            palette: palette,
+            data: data,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        (self.header.xmax - self.header.xmin) as u32 + 1
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.header.ymax - self.header.ymin) as u32 + 1
+    }
+
+    /// resolves data with the palette of the pcx
+    pub fn to_rgba(&self) -> Image {
+        let (image, _) = Image::from_indexed(
+            &self.data,
+            self.width(),
+            self.height(),
+            &self.palette,
+            false,
+        );
+        image
+    }
+}
+
+/// loads skin skin_idx of an md2 model. skin names are relative
+/// to the game directory (e.g. baseq2), which is base_dir
+pub fn load_md2_skin(model: &md2::Model, skin_idx: usize, base_dir: &Path) -> Result<Image> {
+    let name = model
+        .skin_names
+        .get(skin_idx)
+        .ok_or_else(|| Error::index(skin_idx, model.skin_names.len()))?;
+
+    let path = base_dir.join(name.trim_start_matches('/'));
+    let file = std::fs::File::open(&path).map_err(|e| Error::io(e, name))?;
+    let pcx = Pcx::from_reader(&mut BufReader::new(file))?;
+    Ok(pcx.to_rgba())
+}
@@ -0,0 +1,289 @@
+extern crate byteorder;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::Read;
+use std::mem::size_of;
+
+use super::image::Image;
+use super::mdl::{group_index, ST_RAND};
+use super::palette::Palette;
+use super::validation::Issue;
+use super::{Budget, Error, LoadOptions, Result, Section, Tracker};
+
+pub const HEADER_IDENT: i32 = 1347634249;
+pub const HEADER_VERSION: i32 = 1;
+
+/// palette index of transparent pixels
+pub const TRANSPARENT: u8 = 255;
+
+/// how a sprite is turned towards the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// parallel to the view plane, but upright. type 0
+    ParallelUpright,
+    /// faces the viewer, upright. type 1
+    FacingUpright,
+    /// parallel to the view plane. type 2
+    Parallel,
+    /// uses the angles of the entity. type 3
+    Oriented,
+    /// parallel to the view plane, rolled by the entity angles. type 4
+    ParallelOriented,
+}
+
+#[derive(Debug)]
+pub struct Header {
+    pub ident: i32,   // must be equal to 1347634249 or to the string “IDSP”
+    pub version: i32, // 1
+    pub typ: Orientation,
+    pub bounding_radius: f32,
+    pub width: i32, // of the largest frame
+    pub height: i32,
+    pub num_frames: i32,
+    pub beam_length: f32,
+    pub synctype: i32, // 0 synchron. 1 random
+}
+
+/// a single picture. origin is the offset of the upper
+/// left corner from the sprite origin, y points up
+pub struct SimpleFrame {
+    pub origin: [i32; 2],
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>, // width * height palette indices
+}
+
+/// animated frame. time holds the interval
+/// of each subframe, see mdl::group_index
+pub struct GroupFrame {
+    pub time: Vec<f32>,
+    pub frames: Vec<SimpleFrame>,
+}
+
+pub enum Frame {
+    Simple(SimpleFrame), // type 0
+    Group(GroupFrame),   // type !0
+}
+
+impl SimpleFrame {
+    /// converts the palette indices to rgba.
+    /// TRANSPARENT pixels and pixels missing
+    /// from data get alpha 0
+    pub fn rgba(&self, palette: &Palette) -> Image {
+        let width = self.width.max(0) as u32;
+        let height = self.height.max(0) as u32;
+        let len = width as usize * height as usize;
+
+        let mut data = Vec::<u8>::with_capacity(len * 4);
+        for idx in self.data.iter().take(len) {
+            if *idx == TRANSPARENT {
+                data.extend_from_slice(&[0, 0, 0, 0]);
+            } else {
+                let rgb = palette.color(*idx);
+                data.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+            }
+        }
+        data.resize(len * 4, 0);
+
+        Image {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+}
+
+impl Frame {
+    /// simple frames of this frame. one for a simple frame,
+    /// all subframes for a group frame
+    pub fn frames(&self) -> &[SimpleFrame] {
+        match self {
+            Frame::Simple(frame) => std::slice::from_ref(frame),
+            Frame::Group(group) => &group.frames,
+        }
+    }
+
+    /// the frame shown at time seconds, see mdl::group_index.
+    /// None for an empty group
+    pub fn frame_at(&self, time: f32) -> Option<&SimpleFrame> {
+        match self {
+            Frame::Simple(frame) => Some(frame),
+            Frame::Group(group) => {
+                let idx = group_index(&group.time, time);
+                group.frames.get(idx).or(group.frames.last())
+            }
+        }
+    }
+}
+
+pub struct Sprite {
+    pub header: Header,
+    pub frames: Vec<Frame>,
+}
+
+impl Sprite {
+    fn read_i32(reader: &mut dyn Read, msg: &str) -> Result<i32> {
+        reader
+            .read_i32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_f32(reader: &mut dyn Read, msg: &str) -> Result<f32> {
+        reader
+            .read_f32::<LittleEndian>()
+            .map_err(|e| Error::io(e, msg))
+    }
+
+    fn read_header(reader: &mut dyn Read) -> Result<Header> {
+        let ident = Self::read_i32(reader, "failed to read 'ident'.")?;
+        if ident != HEADER_IDENT {
+            return Err(Error::ident(ident, HEADER_IDENT));
+        }
+
+        let version = Self::read_i32(reader, "failed to read 'version'.")?;
+        if version != HEADER_VERSION {
+            return Err(Error::version(version, HEADER_VERSION));
+        }
+
+        let typ = match Self::read_i32(reader, "failed to read 'type'.")? {
+            0 => Orientation::ParallelUpright,
+            1 => Orientation::FacingUpright,
+            2 => Orientation::Parallel,
+            3 => Orientation::Oriented,
+            4 => Orientation::ParallelOriented,
+            typ => {
+                return Err(Error::unsupported(&format!(
+                    "unknown sprite type: {}.",
+                    typ
+                )))
+            }
+        };
+
+        let header = Header {
+            ident: ident,
+            version: version,
+            typ: typ,
+            bounding_radius: Self::read_f32(reader, "failed to read 'bounding_radius'.")?,
+            width: Self::read_i32(reader, "failed to read 'width'.")?,
+            height: Self::read_i32(reader, "failed to read 'height'.")?,
+            num_frames: Self::read_i32(reader, "failed to read 'num_frames'.")?,
+            beam_length: Self::read_f32(reader, "failed to read 'beam_length'.")?,
+            synctype: Self::read_i32(reader, "failed to read 'synctype'.")?,
+        };
+        Ok(header)
+    }
+
+    fn read_simple_frame(reader: &mut dyn Read, budget: &mut Budget) -> Result<SimpleFrame> {
+        let x = Self::read_i32(reader, "failed to read frame origin")?;
+        let y = Self::read_i32(reader, "failed to read frame origin")?;
+        let width = Self::read_i32(reader, "failed to read frame width")?;
+        let height = Self::read_i32(reader, "failed to read frame height")?;
+
+        let len = budget.skin(1, width, height)?;
+        let mut data = vec![0; len];
+        reader
+            .read_exact(&mut data)
+            .map_err(|e| Error::io(e, "failed to read frame data"))?;
+
+        Ok(SimpleFrame {
+            origin: [x, y],
+            width: width,
+            height: height,
+            data: data,
+        })
+    }
+
+    fn read_frames(
+        reader: &mut dyn Read,
+        header: &Header,
+        budget: &mut Budget,
+    ) -> Result<Vec<Frame>> {
+        let num_frames = budget.frames(header.num_frames as i64, size_of::<Frame>())?;
+        let mut frames = Vec::<Frame>::with_capacity(num_frames);
+        for _ in 0..num_frames {
+            let type_ = Self::read_i32(reader, "failed to read frame type")?;
+
+            let frame = if type_ == 0 {
+                Frame::Simple(Self::read_simple_frame(reader, budget)?)
+            } else {
+                let nb = Self::read_i32(reader, "failed to read frame group nb")?;
+
+                let num_times = budget.alloc("frame group nb", nb as i64, size_of::<f32>())?;
+                let mut time = Vec::<f32>::with_capacity(num_times);
+                for _ in 0..num_times {
+                    time.push(Self::read_f32(reader, "failed to read frame group time")?);
+                }
+
+                let nb = budget.frames(nb as i64, size_of::<SimpleFrame>())?;
+                let mut group_frames = Vec::<SimpleFrame>::with_capacity(nb);
+                for _ in 0..nb {
+                    group_frames.push(Self::read_simple_frame(reader, budget)?);
+                }
+
+                Frame::Group(GroupFrame {
+                    time: time,
+                    frames: group_frames,
+                })
+            };
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+
+    pub fn from_reader(reader: &mut dyn Read) -> Result<Self> {
+        Self::from_reader_with(reader, &LoadOptions::default())
+    }
+
+    pub fn from_reader_with(reader: &mut dyn Read, options: &LoadOptions) -> Result<Self> {
+        let mut reader = Tracker::new(reader);
+        let mut budget = Budget::new(&options.limits);
+        let header =
+            Self::read_header(&mut reader).map_err(|e| e.at(Section::Header, reader.last))?;
+        let frames = Self::read_frames(&mut reader, &header, &mut budget)
+            .map_err(|e| e.at(Section::Frames, reader.last))?;
+
+        let sprite = Sprite {
+            header: header,
+            frames: frames,
+        };
+        if options.strict {
+            Error::check(sprite.validate())?;
+        }
+        Ok(sprite)
+    }
+
+    /// checks for frames and empty groups. frame indices
+    /// of EmptyGroup index frames
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::<Issue>::new();
+        if self.frames.is_empty() {
+            issues.push(Issue::NoFrames);
+        }
+        for (idx, frame) in self.frames.iter().enumerate() {
+            if frame.frames().is_empty() {
+                issues.push(Issue::EmptyGroup { frame: idx });
+            }
+        }
+        issues
+    }
+
+    /// offset quake adds to the time of group frames, see mdl::Model::syncbase
+    pub fn syncbase(&self, random: f32) -> f32 {
+        if self.header.synctype == ST_RAND {
+            random
+        } else {
+            0.0
+        }
+    }
+
+    /// decodes the frame shown at time seconds of frame frame_idx to rgba.
+    /// also returns its origin. None if there is no such frame
+    pub fn rgba_at(
+        &self,
+        frame_idx: usize,
+        time: f32,
+        palette: &Palette,
+    ) -> Option<(Image, [i32; 2])> {
+        let frame = self.frames.get(frame_idx)?.frame_at(time)?;
+        Some((frame.rgba(palette), frame.origin))
+    }
+}
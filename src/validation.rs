@@ -0,0 +1,185 @@
+use super::NORMALS;
+
+/// the kind of element a count or limit refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Skins,
+    Vertices,
+    Texcoords,
+    Triangles,
+    Frames,
+    Tags,
+    Surfaces,
+    Shaders,
+}
+
+/// a problem found by validate(). triangle and vertex
+/// indices count across all surfaces of an md3, in
+/// the same order as AliasModel::faces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    NoFrames,
+    /// more elements than the engine supports
+    TooMany {
+        item: Item,
+        count: usize,
+        max: usize,
+    },
+    /// a triangle references a vertex that does not exist
+    VertexIndex {
+        triangle: usize,
+        index: i64,
+        len: usize,
+    },
+    /// a triangle references a texcoord that does not exist
+    TexcoordIndex {
+        triangle: usize,
+        index: i64,
+        len: usize,
+    },
+    /// a gl command references a vertex that does not exist
+    CommandIndex {
+        command: usize,
+        index: i64,
+        len: usize,
+    },
+    /// compressed normal outside of NORMALS
+    NormalIndex {
+        frame: usize,
+        vertex: usize,
+        index: u8,
+    },
+    /// a frame does not have the same number of vertices as the model
+    VertexCount {
+        frame: usize,
+        count: usize,
+        expected: usize,
+    },
+    /// an md3 surface does not have a vertex list for every frame
+    SurfaceFrames {
+        surface: usize,
+        count: usize,
+        expected: usize,
+    },
+    /// an mdl frame group without frames
+    EmptyGroup {
+        frame: usize,
+    },
+    /// an mdl frame group with a different number of times than frames
+    GroupTimes {
+        frame: usize,
+        count: usize,
+        expected: usize,
+    },
+    /// skin data does not match skin_width * skin_height
+    SkinSize {
+        skin: usize,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::NoFrames => write!(f, "model has no frames"),
+            Issue::TooMany { item, count, max } => {
+                write!(f, "too many {:?}. count: {}. max: {}", item, count, max)
+            }
+            Issue::VertexIndex {
+                triangle,
+                index,
+                len,
+            } => write!(
+                f,
+                "triangle {} references vertex {}. num vertices: {}",
+                triangle, index, len
+            ),
+            Issue::TexcoordIndex {
+                triangle,
+                index,
+                len,
+            } => write!(
+                f,
+                "triangle {} references texcoord {}. num texcoords: {}",
+                triangle, index, len
+            ),
+            Issue::CommandIndex {
+                command,
+                index,
+                len,
+            } => write!(
+                f,
+                "command {} references vertex {}. num vertices: {}",
+                command, index, len
+            ),
+            Issue::NormalIndex {
+                frame,
+                vertex,
+                index,
+            } => write!(
+                f,
+                "vertex {} of frame {} has normal index {}",
+                vertex, frame, index
+            ),
+            Issue::VertexCount {
+                frame,
+                count,
+                expected,
+            } => write!(
+                f,
+                "frame {} has {} vertices. expected: {}",
+                frame, count, expected
+            ),
+            Issue::SurfaceFrames {
+                surface,
+                count,
+                expected,
+            } => write!(
+                f,
+                "surface {} has {} frames. expected: {}",
+                surface, count, expected
+            ),
+            Issue::EmptyGroup { frame } => write!(f, "frame group {} is empty", frame),
+            Issue::GroupTimes {
+                frame,
+                count,
+                expected,
+            } => write!(
+                f,
+                "frame group {} has {} times. expected: {}",
+                frame, count, expected
+            ),
+            Issue::SkinSize {
+                skin,
+                len,
+                expected,
+            } => write!(f, "skin {} has {} bytes. expected: {}", skin, len, expected),
+        }
+    }
+}
+
+pub(crate) fn check_count(issues: &mut Vec<Issue>, item: Item, count: usize, max: u16) {
+    if count > max as usize {
+        issues.push(Issue::TooMany {
+            item: item,
+            count: count,
+            max: max as usize,
+        });
+    }
+}
+
+pub(crate) fn check_normal(issues: &mut Vec<Issue>, frame: usize, vertex: usize, index: u8) {
+    if index as usize >= NORMALS.len() {
+        issues.push(Issue::NormalIndex {
+            frame: frame,
+            vertex: vertex,
+            index: index,
+        });
+    }
+}
+
+/// true if index addresses one of len elements
+pub(crate) fn in_range(index: i64, len: usize) -> bool {
+    index >= 0 && (index as u64) < len as u64
+}
@@ -0,0 +1,428 @@
+extern crate model_reader;
+
+use model_reader::animation::{
+    clips_from_frames, clips_from_names, md2_clips, Animator, Clip, Event, PlayMode,
+};
+use model_reader::flat_model::FlatModel;
+use model_reader::md2::*;
+use model_reader::mdl;
+use model_reader::AliasModel;
+
+mod common;
+use common::{assert_near, md2_model, mdl_model};
+
+fn clip(first: usize, last: usize, fps: f32) -> Clip {
+    Clip {
+        name: "run".to_string(),
+        first,
+        last,
+        fps,
+    }
+}
+
+/// one vertex, moving along x and turning its normal from +x to +y
+fn flat() -> FlatModel {
+    FlatModel {
+        vertices: vec![vec![[0.0, 0.0, 0.0]], vec![[2.0, 0.0, 0.0]]],
+        normals: vec![vec![[1.0, 0.0, 0.0]], vec![[0.0, 1.0, 0.0]]],
+        tangents: Vec::new(),
+        texcoords: vec![(0.0, 0.0)],
+        indices: Vec::new(),
+    }
+}
+
+#[test]
+fn clip_frames_at() {
+    let clip = clip(4, 7, 10.0);
+    assert_eq!(clip.frames_at(0.0), (4, 5, 0.0));
+    let (a, b, blend) = clip.frames_at(0.25);
+    assert_eq!((a, b), (6, 7));
+    assert!((blend - 0.5).abs() < 1e-5);
+    // the last frame blends back to the first one
+    let (a, b, _) = clip.frames_at(0.35);
+    assert_eq!((a, b), (7, 4));
+    // time wraps around, also backwards
+    assert_eq!(clip.frames_at(0.4).0, 4);
+    assert_eq!(clip.frames_at(-0.05).0, 7);
+
+    assert_eq!(self::clip(3, 3, 10.0).frames_at(1.234), (3, 3, 0.0));
+    assert_eq!(self::clip(3, 5, 0.0).frames_at(1.0), (3, 3, 0.0));
+}
+
+#[test]
+fn clip_empty() {
+    // the first frame comes after the last
+    let empty = clip(5, 3, 10.0);
+    assert_eq!(empty.num_frames(), 0);
+    assert_eq!(empty.frames_at(1.0), (5, 5, 0.0));
+    assert_eq!(clip(3, 3, 10.0).num_frames(), 1);
+
+    let mut animator = Animator::new(vec![empty]);
+    for &mode in &[PlayMode::Loop, PlayMode::Once, PlayMode::PingPong] {
+        animator.play(0, mode);
+        animator.update(0.25);
+    }
+}
+
+#[test]
+fn flat_model_pose() {
+    let model = flat();
+    let pose = model.pose(0, 1, 0.5);
+    assert_near(pose.positions[0], [1.0, 0.0, 0.0]);
+    let half = 0.5f32.sqrt();
+    assert_near(pose.normals[0], [half, half, 0.0]);
+
+    assert_eq!(model.pose(0, 1, 0.0).positions, model.vertices[0]);
+    assert_eq!(model.pose(0, 1, 1.0).normals, model.normals[1]);
+
+    let pose = model.sample(&clip(0, 1, 4.0), 0.0625);
+    assert_near(pose.positions[0], [0.5, 0.0, 0.0]);
+}
+
+#[test]
+fn opposite_normals() {
+    let mut model = flat();
+    model.normals[1][0] = [-1.0, 0.0, 0.0];
+    assert_eq!(model.pose(0, 1, 0.4).normals[0], [1.0, 0.0, 0.0]);
+    assert_eq!(model.pose(0, 1, 0.6).normals[0], [-1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn alias_model_pose() {
+    let frame = |z: u8, normal_idx: u8| Frame {
+        scale: [1.0, 1.0, 1.0],
+        translate: [0.0, 0.0, 0.0],
+        name: "run".to_string(),
+        vertices: vec![Vertex {
+            v: [0, 0, z],
+            normal_idx,
+        }],
+    };
+    // a single vertex without faces
+    let mut model = md2_model();
+    model.faces.clear();
+    model.frames = vec![frame(0, 5), frame(10, 6)];
+
+    let pose = model.pose(0, 1, 0.25);
+    assert_near(pose.positions[0], [0.0, 0.0, 2.5]);
+    let normal = pose.normals[0];
+    let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    assert!((len - 1.0).abs() < 1e-5);
+
+    // the default implementation agrees with the flat model
+    let flat = FlatModel::from_md2(&model);
+    let clip = clip(0, 1, 10.0);
+    assert_eq!(model.sample(&clip, 0.07), flat.sample(&clip, 0.07));
+
+    let clips = clips_from_frames(&model, 10.0);
+    assert_eq!(clips.len(), 1);
+    assert_eq!(
+        (clips[0].name.as_str(), clips[0].first, clips[0].last),
+        ("run", 0, 1)
+    );
+}
+
+#[test]
+fn clips_from_frame_names() {
+    let names = [
+        "stand01", "stand02", "stand03", "run1", "run2", "pain", "run3", "death10", "death11",
+    ];
+    let clips = clips_from_names(&names, 10.0);
+    let ranges: Vec<(&str, usize, usize)> = clips
+        .iter()
+        .map(|c| (c.name.as_str(), c.first, c.last))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("stand", 0, 2),
+            ("run", 3, 4),
+            ("pain", 5, 5),
+            ("run", 6, 6),
+            ("death", 7, 8),
+        ]
+    );
+    assert!(clips.iter().all(|c| c.fps == 10.0));
+    assert!(clips_from_names(&[], 10.0).is_empty());
+
+    // a counter that starts over begins a new clip
+    let clips = clips_from_names(&["idle1", "idle2", "idle1", "idle2"], 10.0);
+    assert_eq!(clips.len(), 2);
+    assert_eq!((clips[1].first, clips[1].last), (2, 3));
+}
+
+/// frame names of the stock quake 2 player models
+fn q2_player_names() -> Vec<String> {
+    let runs: [(&str, usize, bool); 20] = [
+        // name, frames, two digit counter
+        ("stand", 40, true),
+        ("run", 6, false),
+        ("attack", 8, false),
+        ("pain1", 4, true),
+        ("pain2", 4, true),
+        ("pain3", 4, true),
+        ("jump", 6, false),
+        ("flip", 12, true),
+        ("salute", 11, true),
+        ("taunt", 17, true),
+        ("wave", 11, true),
+        ("point", 12, true),
+        ("crstnd", 19, true),
+        ("crwalk", 6, false),
+        ("crattak", 9, false),
+        ("crpain", 4, false),
+        ("crdeath", 5, false),
+        ("death1", 6, true),
+        ("death2", 6, true),
+        ("death3", 8, true),
+    ];
+    let mut names = Vec::new();
+    for (name, count, two_digits) in &runs {
+        for i in 1..=*count {
+            if *two_digits {
+                names.push(format!("{}{:02}", name, i));
+            } else {
+                names.push(format!("{}{}", name, i));
+            }
+        }
+    }
+    names
+}
+
+#[test]
+fn clips_from_q2_player_names() {
+    let names = q2_player_names();
+    assert_eq!(names.len(), 198);
+    assert_eq!(names[54], "pain101");
+    assert_eq!(names[197], "death308");
+
+    let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+    let clips = clips_from_names(&names, 10.0);
+    let found: Vec<&str> = clips.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        found,
+        vec![
+            "stand", "run", "attack", "pain1", "pain2", "pain3", "jump", "flip", "salute", "taunt",
+            "wave", "point", "crstnd", "crwalk", "crattak", "crpain", "crdeath", "death1",
+            "death2", "death3",
+        ]
+    );
+    // the same ranges as the preset, which adds boom
+    assert_eq!(clips.len(), ANIMATIONS.len() - 1);
+    for (clip, anim) in clips.iter().zip(ANIMATIONS.iter()) {
+        assert_eq!(
+            (clip.first, clip.last),
+            (anim[0] as usize, anim[1] as usize),
+            "{}",
+            clip.name
+        );
+    }
+}
+
+#[test]
+fn clips_across_hundreds() {
+    let ranges = |names: &[&str]| -> Vec<(String, usize, usize)> {
+        clips_from_names(names, 10.0)
+            .into_iter()
+            .map(|c| (c.name, c.first, c.last))
+            .collect()
+    };
+
+    // whole numbers counting up are not split into a group digit
+    assert_eq!(
+        ranges(&["run98", "run99", "run100", "run101", "idle1"]),
+        vec![("run".to_string(), 0, 3), ("idle".to_string(), 4, 4)]
+    );
+    let names: Vec<String> = (0..=120).map(|i| format!("frame{:03}", i)).collect();
+    let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+    assert_eq!(ranges(&names), vec![("frame".to_string(), 0, 120)]);
+    assert_eq!(
+        ranges(&["walk199", "walk200", "walk201"]),
+        vec![("walk".to_string(), 0, 2)]
+    );
+}
+
+#[test]
+fn md2_preset() {
+    let clips = md2_clips();
+    assert_eq!(clips.len(), ANIMATIONS.len());
+    // the ranges follow each other without gaps
+    assert_eq!(clips[0].first, 0);
+    for pair in clips.windows(2) {
+        assert!(pair[0].first <= pair[0].last, "{}", pair[0].name);
+        assert_eq!(pair[0].last + 1, pair[1].first, "{}", pair[1].name);
+    }
+}
+
+fn animator() -> Animator {
+    // frames 0..3 and 4..5 at 10 fps
+    Animator::new(vec![clip(0, 3, 10.0), clip(4, 5, 10.0)])
+}
+
+fn near(frames: Option<(usize, usize, f32)>, a: usize, b: usize, blend: f32) {
+    let (fa, fb, fblend) = frames.unwrap();
+    assert_eq!((fa, fb), (a, b));
+    assert!((fblend - blend).abs() < 1e-4, "{} != {}", fblend, blend);
+}
+
+#[test]
+fn animator_loop() {
+    let mut animator = animator();
+    assert_eq!(animator.frames(), None);
+    assert!(animator.update(0.1).is_empty());
+
+    animator.play(0, PlayMode::Loop);
+    assert!(animator.update(0.25).is_empty());
+    near(animator.frames(), 2, 3, 0.5);
+    assert_eq!(animator.update(0.1), vec![]);
+    near(animator.frames(), 3, 0, 0.5);
+    assert_eq!(animator.update(0.1), vec![Event::Looped { clip: 0 }]);
+    near(animator.frames(), 0, 1, 0.5);
+}
+
+#[test]
+fn animator_once() {
+    let mut animator = animator();
+    animator.play(0, PlayMode::Once);
+    assert!(animator.update(0.2).is_empty());
+    assert!(!animator.finished());
+    assert_eq!(animator.update(0.15), vec![Event::Finished { clip: 0 }]);
+    assert!(animator.finished());
+    // holds the last frame without more events
+    near(animator.frames(), 3, 3, 0.0);
+    assert!(animator.update(1.0).is_empty());
+    near(animator.frames(), 3, 3, 0.0);
+}
+
+#[test]
+fn animator_ping_pong() {
+    let mut animator = animator();
+    animator.play(0, PlayMode::PingPong);
+    animator.update(0.25);
+    near(animator.frames(), 2, 3, 0.5);
+    animator.update(0.1);
+    near(animator.frames(), 3, 2, 0.5);
+    assert!(animator.update(0.2).is_empty());
+    near(animator.frames(), 1, 0, 0.5);
+    assert_eq!(animator.update(0.1), vec![Event::Looped { clip: 0 }]);
+    near(animator.frames(), 0, 1, 0.5);
+}
+
+#[test]
+fn animator_crossfade() {
+    let model = FlatModel {
+        vertices: (0..6).map(|i| vec![[i as f32, 0.0, 0.0]]).collect(),
+        normals: vec![vec![[0.0, 0.0, 1.0]]; 6],
+        tangents: Vec::new(),
+        texcoords: vec![(0.0, 0.0)],
+        indices: Vec::new(),
+    };
+    let mut animator = animator();
+    assert_eq!(animator.find("run"), Some(0));
+    animator.play(0, PlayMode::Once);
+    animator.update(1.0);
+
+    animator.crossfade(1, PlayMode::Once, 0.2);
+    assert_eq!(animator.clip(), Some(1));
+    let pose = animator
+        .pose(|a, b, blend| model.pose(a, b, blend))
+        .unwrap();
+    assert_near(pose.positions[0], [3.0, 0.0, 0.0]);
+
+    // half way from frame 3 to frame 5
+    animator.update(0.1);
+    let pose = animator
+        .pose(|a, b, blend| model.pose(a, b, blend))
+        .unwrap();
+    assert_near(pose.positions[0], [4.0, 0.0, 0.0]);
+
+    animator.update(0.1);
+    let pose = animator
+        .pose(|a, b, blend| model.pose(a, b, blend))
+        .unwrap();
+    assert_near(pose.positions[0], [5.0, 0.0, 0.0]);
+}
+
+#[test]
+fn animator_synctype() {
+    let mut model = mdl_model();
+    model.header.synctype = mdl::ST_SYNC;
+
+    let mut animator = Animator::for_mdl(vec![clip(0, 3, 10.0)], &model, 0.5);
+    animator.play(0, PlayMode::Loop);
+    assert_eq!(animator.time(), 0.0);
+
+    model.header.synctype = mdl::ST_RAND;
+    let mut animator = Animator::for_mdl(vec![clip(0, 3, 10.0)], &model, 0.5);
+    animator.play(0, PlayMode::Loop);
+    // half of the four frame cycle
+    near(animator.frames(), 2, 3, 0.0);
+
+    assert_eq!(model.syncbase(0.25), 0.25);
+    model.header.synctype = mdl::ST_SYNC;
+    assert_eq!(model.syncbase(0.25), 0.0);
+}
+
+#[test]
+fn group_times() {
+    // images end at 0.1, 0.3 and 0.6, then the cycle repeats
+    let times = [0.1, 0.3, 0.6];
+    let shown: Vec<usize> = [0.0, 0.05, 0.1, 0.29, 0.3, 0.59, 0.65, 0.75, -0.1]
+        .iter()
+        .map(|t| mdl::group_index(&times, *t))
+        .collect();
+    assert_eq!(shown, vec![0, 0, 1, 1, 2, 2, 0, 1, 2]);
+
+    assert_eq!(mdl::group_index(&[], 1.0), 0);
+    assert_eq!(mdl::group_index(&[0.0, 0.0], 1.0), 0);
+    assert_eq!(mdl::group_index(&[0.1, 0.2], f32::NAN), 0);
+}
+
+#[test]
+fn group_frames_and_skins() {
+    let simple = |name: &str| mdl::SimpleFrame {
+        bboxmin: mdl::Vertex {
+            v: [0, 0, 0],
+            normal_idx: 0,
+        },
+        bboxmax: mdl::Vertex {
+            v: [0, 0, 0],
+            normal_idx: 0,
+        },
+        name: name.to_string(),
+        verts: Vec::new(),
+    };
+    let group = mdl::Frame::Group(mdl::GroupFrame {
+        min: mdl::Vertex {
+            v: [0, 0, 0],
+            normal_idx: 0,
+        },
+        max: mdl::Vertex {
+            v: [0, 0, 0],
+            normal_idx: 0,
+        },
+        time: vec![0.1, 0.2],
+        frames: vec![simple("flame1"), simple("flame2")],
+    });
+    assert_eq!(group.frame_at(0.05).unwrap().name, "flame1");
+    assert_eq!(group.frame_at(0.15).unwrap().name, "flame2");
+    assert_eq!(group.frame_at(0.25).unwrap().name, "flame1");
+    assert_eq!(
+        mdl::Frame::Simple(simple("stand"))
+            .frame_at(1.0)
+            .unwrap()
+            .name,
+        "stand"
+    );
+
+    let skin = mdl::Skin::Group(mdl::GroupSkin {
+        nb: 2,
+        time: vec![0.5, 1.0],
+        data: vec![0; 16],
+    });
+    assert_eq!(skin.image_at(0.7), 1);
+    assert_eq!(
+        mdl::Skin::Simple(mdl::SimpleSkin { data: vec![0; 8] }).image_at(0.7),
+        0
+    );
+}
@@ -0,0 +1,287 @@
+//! helpers shared by the integration tests. every test
+//! file only uses some of them
+#![allow(dead_code)]
+
+use model_reader::{md2, mdl};
+
+pub fn push_i32(buf: &mut Vec<u8>, v: i32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_i16(buf: &mut Vec<u8>, v: i16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_f32(buf: &mut Vec<u8>, v: f32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_vec3(buf: &mut Vec<u8>, v: [f32; 3]) {
+    for x in &v {
+        push_f32(buf, *x);
+    }
+}
+
+/// name zero padded to len bytes
+pub fn push_name(buf: &mut Vec<u8>, name: &str, len: usize) {
+    let mut bytes = name.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    buf.extend_from_slice(&bytes);
+}
+
+/// overwrites the i32 at offset, for patching a single header field
+pub fn set_i32(buf: &mut [u8], offset: usize, v: i32) {
+    buf[offset..offset + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+pub fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+pub fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+    assert_near_eps(actual, expected, 1e-5);
+}
+
+pub fn assert_near_eps(actual: [f32; 3], expected: [f32; 3], eps: f32) {
+    for i in 0..3 {
+        assert!(
+            (actual[i] - expected[i]).abs() < eps,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+/// an 84 byte mdl header without skins, vertices, faces or frames
+pub fn mdl_header() -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_i32(&mut buf, mdl::HEADER_IDENT);
+    push_i32(&mut buf, mdl::HEADER_VERSION);
+    push_vec3(&mut buf, [0.5, 1.5, 2.5]); // scale
+    push_vec3(&mut buf, [-10.0, -20.0, -30.0]); // translate
+    push_f32(&mut buf, 42.25); // boundigradius
+    push_vec3(&mut buf, [0.0, 0.0, 24.0]); // eyeposition
+    push_i32(&mut buf, 0); // num_skins
+    push_i32(&mut buf, 296); // skin_width
+    push_i32(&mut buf, 194); // skin_height
+    push_i32(&mut buf, 0); // num_verices
+    push_i32(&mut buf, 0); // num_faces
+    push_i32(&mut buf, 0); // num_frames
+    push_i32(&mut buf, 1); // synctype
+    push_i32(&mut buf, 8); // flags
+    push_f32(&mut buf, 3.75); // size
+    buf
+}
+
+/// a 68 byte md2 header with empty sections at the offsets
+pub fn md2_header() -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_i32(&mut buf, md2::HEADER_IDENT);
+    push_i32(&mut buf, md2::HEADER_VERSION);
+    push_i32(&mut buf, 256); // skin_width
+    push_i32(&mut buf, 128); // skin_height
+    push_i32(&mut buf, 40); // frame_size
+    for _ in 0..6 {
+        push_i32(&mut buf, 0); // num_skins .. num_frames
+    }
+    for offset in &[68, 132, 196, 260, 324, 388] {
+        push_i32(&mut buf, *offset); // offset_skins .. offset_end
+    }
+    buf
+}
+
+pub fn mdl_vertex(x: u8, normal: u8) -> mdl::Vertex {
+    mdl::Vertex {
+        v: [x, 255 - x, x / 2],
+        normal_idx: normal,
+    }
+}
+
+/// three vertices, starting at x, with the normals 1, 2 and 3
+pub fn mdl_frame(name: &str, x: u8) -> mdl::SimpleFrame {
+    mdl::SimpleFrame {
+        bboxmin: mdl_vertex(0, 0),
+        bboxmax: mdl_vertex(255, 0),
+        name: name.to_string(),
+        verts: vec![mdl_vertex(x, 1), mdl_vertex(x + 1, 2), mdl_vertex(x + 2, 3)],
+    }
+}
+
+/// a 4x2 simple skin and a group of two, a front and a back face
+/// sharing vertex 1 on the seam, a simple frame and a group of three
+pub fn mdl_model() -> mdl::Model {
+    mdl::Model {
+        header: mdl::Header {
+            ident: mdl::HEADER_IDENT,
+            version: mdl::HEADER_VERSION,
+            scale: [0.5, 0.25, 2.0],
+            translate: [-8.0, 4.0, 16.0],
+            boundigradius: 30.5,
+            eyeposition: [0.0, 0.0, 22.0],
+            num_skins: 0,
+            skin_width: 4,
+            skin_height: 2,
+            num_verices: 0,
+            num_faces: 0,
+            num_frames: 0,
+            synctype: 1,
+            flags: 4,
+            size: 7.5,
+        },
+        skins: vec![
+            mdl::Skin::Simple(mdl::SimpleSkin {
+                data: vec![0, 1, 2, 3, 224, 225, 254, 255],
+            }),
+            mdl::Skin::Group(mdl::GroupSkin {
+                nb: 2,
+                time: vec![0.1, 0.2],
+                data: (0..16).collect(),
+            }),
+        ],
+        texcoords: vec![
+            mdl::TexCoord {
+                onseam: 0,
+                s: 0,
+                t: 0,
+            },
+            mdl::TexCoord {
+                onseam: 32,
+                s: 3,
+                t: 0,
+            },
+            mdl::TexCoord {
+                onseam: 0,
+                s: 0,
+                t: 1,
+            },
+        ],
+        triangles: vec![
+            mdl::Triangle {
+                facefront: 1,
+                vertex: [0, 1, 2],
+            },
+            mdl::Triangle {
+                facefront: 0,
+                vertex: [2, 1, 0],
+            },
+        ],
+        frames: vec![
+            mdl::Frame::Simple(mdl_frame("stand1", 10)),
+            mdl::Frame::Group(mdl::GroupFrame {
+                min: mdl_vertex(0, 0),
+                max: mdl_vertex(200, 0),
+                time: vec![0.1, 0.2, 0.3],
+                frames: vec![
+                    mdl_frame("flame1", 20),
+                    mdl_frame("flame2", 30),
+                    mdl_frame("flame3", 40),
+                ],
+            }),
+        ],
+    }
+}
+
+/// one face with three vertices, two frames and two gl commands
+pub fn md2_model() -> md2::Model {
+    let frame = |name: &str, z: u8| md2::Frame {
+        scale: [0.5, 0.25, 2.0],
+        translate: [-8.0, 4.0, 16.0],
+        name: name.to_string(),
+        vertices: vec![
+            md2::Vertex {
+                v: [0, 0, z],
+                normal_idx: 5,
+            },
+            md2::Vertex {
+                v: [255, 0, z],
+                normal_idx: 6,
+            },
+            md2::Vertex {
+                v: [0, 255, z],
+                normal_idx: 161,
+            },
+        ],
+    };
+
+    md2::Model {
+        header: md2::Header {
+            ident: md2::HEADER_IDENT,
+            version: md2::HEADER_VERSION,
+            skin_width: 64,
+            skin_height: 32,
+            frame_size: 0,
+            num_skins: 0,
+            num_vertices: 0,
+            num_texcoords: 0,
+            num_faces: 0,
+            num_gl_cmds: 0,
+            num_frames: 0,
+            offset_skins: 0,
+            offset_texcoords: 0,
+            offset_faces: 0,
+            offset_frames: 0,
+            offset_gl_cmds: 0,
+            offset_end: 0,
+        },
+        skin_names: vec!["models/test/skin.pcx".to_string()],
+        texcoords: vec![
+            md2::TexCoord { s: 0, t: 0 },
+            md2::TexCoord { s: 63, t: 0 },
+            md2::TexCoord { s: 0, t: 31 },
+        ],
+        faces: vec![md2::Triangle {
+            vertex: [0, 1, 2],
+            st_idx: [0, 1, 2],
+        }],
+        frames: vec![frame("stand01", 0), frame("stand02", 10)],
+        commands: vec![
+            md2::Command {
+                typ: md2::CommandType::Strip,
+                packets: vec![
+                    md2::CommandPacket {
+                        s: 0.0,
+                        t: 0.0,
+                        i: 0,
+                    },
+                    md2::CommandPacket {
+                        s: 1.0,
+                        t: 0.0,
+                        i: 1,
+                    },
+                    md2::CommandPacket {
+                        s: 0.0,
+                        t: 1.0,
+                        i: 2,
+                    },
+                ],
+            },
+            md2::Command {
+                typ: md2::CommandType::Fan,
+                packets: vec![
+                    md2::CommandPacket {
+                        s: 0.0,
+                        t: 1.0,
+                        i: 2,
+                    },
+                    md2::CommandPacket {
+                        s: 1.0,
+                        t: 0.0,
+                        i: 1,
+                    },
+                    md2::CommandPacket {
+                        s: 0.0,
+                        t: 0.0,
+                        i: 0,
+                    },
+                ],
+            },
+        ],
+    }
+}
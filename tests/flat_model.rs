@@ -0,0 +1,289 @@
+extern crate model_reader;
+
+use model_reader::flat_model::{FlatModel, NormalWeighting};
+use model_reader::gltf;
+
+mod common;
+use common::assert_near;
+
+/// two faces folded along the y axis. the vertices of the fold
+/// are duplicated, like from_model does on a seam. face a lies in
+/// the z = 0 plane and faces +z, face b lies in the x = 0 plane,
+/// faces +x and is twice as large
+fn fold() -> FlatModel {
+    let vertices = vec![
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 2.0],
+        [0.0, 1.0, 0.0],
+    ];
+    FlatModel {
+        normals: vec![vec![[0.0, 0.0, 0.0]; vertices.len()]],
+        vertices: vec![vertices],
+        tangents: Vec::new(),
+        texcoords: vec![(0.0, 0.0); 6],
+        indices: vec![(0, 1, 2), (3, 4, 5)],
+    }
+}
+
+#[test]
+fn recompute_normals_area() {
+    let mut model = fold();
+    model.recompute_normals(NormalWeighting::Area);
+    let normals = &model.normals[0];
+
+    let len = 5f32.sqrt();
+    assert_near(normals[0], [2.0 / len, 0.0, 1.0 / len]);
+    assert_near(normals[2], [0.0, 0.0, 1.0]);
+    assert_near(normals[4], [1.0, 0.0, 0.0]);
+    // welded duplicates share the normal
+    assert_eq!(normals[0], normals[3]);
+    assert_eq!(normals[1], normals[5]);
+}
+
+#[test]
+fn recompute_normals_angle() {
+    let mut model = fold();
+    model.recompute_normals(NormalWeighting::Angle);
+    let normals = &model.normals[0];
+
+    let half = 0.5f32.sqrt();
+    assert_near(normals[0], [half, 0.0, half]);
+    assert_eq!(normals[0], normals[3]);
+    assert_near(normals[2], [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn recompute_normals_keeps_unused() {
+    let mut model = fold();
+    model.vertices[0].push([5.0, 5.0, 5.0]);
+    model.normals[0].push([0.0, 1.0, 0.0]);
+    model.recompute_normals(NormalWeighting::Area);
+    assert_eq!(model.normals[0][6], [0.0, 1.0, 0.0]);
+}
+
+/// a unit quad in the z = 0 plane facing +z with two frames, the
+/// second moved along z. s runs along +x and t down the skin along -y
+fn quad(mirrored: bool) -> FlatModel {
+    let vertices = vec![
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let moved = vertices.iter().map(|v| [v[0], v[1], 1.0]).collect();
+    let s = |x: f32| if mirrored { 1.0 - x } else { x };
+    FlatModel {
+        normals: vec![vec![[0.0, 0.0, 1.0]; 4]; 2],
+        vertices: vec![vertices, moved],
+        tangents: Vec::new(),
+        texcoords: vec![(s(0.0), 1.0), (s(0.0), 0.0), (s(1.0), 0.0), (s(1.0), 1.0)],
+        indices: vec![(0, 1, 2), (0, 2, 3)],
+    }
+}
+
+#[test]
+fn generate_tangents() {
+    let mut model = quad(false);
+    model.generate_tangents();
+    assert_eq!(model.tangents.len(), 2);
+    for frame in &model.tangents {
+        for tangent in frame {
+            assert_near([tangent[0], tangent[1], tangent[2]], [1.0, 0.0, 0.0]);
+            // cross(normal, tangent) is +y, up the skin
+            assert_eq!(tangent[3], 1.0);
+        }
+    }
+
+    let mut mirrored = quad(true);
+    mirrored.generate_tangents();
+    for tangent in &mirrored.tangents[0] {
+        assert_near([tangent[0], tangent[1], tangent[2]], [-1.0, 0.0, 0.0]);
+        assert_eq!(tangent[3], -1.0);
+    }
+}
+
+/// a quarter of a unit cylinder around the z axis, split into two
+/// faces per 45 degrees. the normals point away from the axis,
+/// s runs around the cylinder and t down the skin along -z
+fn quarter_cylinder() -> FlatModel {
+    let angles = [0f32, 45.0, 90.0];
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut texcoords = Vec::new();
+    for angle in &angles {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        for z in &[0.0, 1.0] {
+            vertices.push([cos, sin, *z]);
+            normals.push([cos, sin, 0.0]);
+            texcoords.push((angle / 90.0, 1.0 - z));
+        }
+    }
+    // vertex 2 * i is at the bottom, 2 * i + 1 at the top
+    let indices = (0..angles.len() - 1)
+        .flat_map(|i| {
+            let (b, t) = (2 * i, 2 * i + 1);
+            vec![(b, t, b + 2), (t, t + 2, b + 2)]
+        })
+        .collect();
+    FlatModel {
+        vertices: vec![vertices],
+        normals: vec![normals],
+        tangents: Vec::new(),
+        texcoords,
+        indices,
+    }
+}
+
+#[test]
+fn generate_tangents_curved() {
+    let mut model = quarter_cylinder();
+    model.generate_tangents();
+
+    // the tangent follows the circle, the faces are flat
+    // but their tangents are projected onto the normals
+    for (v, tangent) in model.tangents[0].iter().enumerate() {
+        let angle = (45.0 * (v / 2) as f32).to_radians();
+        assert_near(
+            [tangent[0], tangent[1], tangent[2]],
+            [-angle.sin(), angle.cos(), 0.0],
+        );
+        // cross(normal, tangent) is +z, up the skin
+        assert_eq!(tangent[3], 1.0);
+    }
+}
+
+/// two bands of quads around an arc, the upper one narrowing and
+/// leaning back. s runs forward over the first two columns and back
+/// over the last, mirroring the skin around the third column. the
+/// bands meet at a seam, the upper one uses another part of the skin
+fn mirrored_strip() -> FlatModel {
+    // z, texcoord t, radius and tilt of the normal of every row
+    let rows: [(f32, f32, f32, f32); 4] = [
+        (0.0, 1.0, 1.0, 0.0),
+        (1.0, 0.5, 1.0, 0.0),
+        (1.0, 0.4, 1.0, 0.0),
+        (2.0, 0.0, 0.7, 0.5),
+    ];
+    let columns = [(0f32, 0.0), (40.0, 0.5), (80.0, 1.0), (120.0, 0.5)];
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut texcoords = Vec::new();
+    for &(z, t, radius, tilt) in &rows {
+        for &(angle, s) in &columns {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            vertices.push([radius * cos, radius * sin, z]);
+            let len = (1.0 + tilt * tilt).sqrt();
+            normals.push([cos / len, sin / len, tilt / len]);
+            texcoords.push((s, t));
+        }
+    }
+    let mut indices = Vec::new();
+    for &(bottom, top) in &[(0, 4), (8, 12)] {
+        for i in 0..3 {
+            let (b, t) = (bottom + i, top + i);
+            indices.push((b, t, b + 1));
+            indices.push((t, t + 1, b + 1));
+        }
+    }
+    FlatModel {
+        vertices: vec![vertices],
+        normals: vec![normals],
+        tangents: Vec::new(),
+        texcoords,
+        indices,
+    }
+}
+
+/// the tangents of the corners of mirrored_strip, three per face, from
+/// the reference implementation (bevy_mikktspace 0.15, a port of the
+/// original c code) run on the counter clockwise winding with t flipped
+const MIRRORED_STRIP_TANGENTS: [[f32; 4]; 36] = [
+    [0.0, 1.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [0.0, 1.0, 0.0, 1.0],
+    [-0.64278764, 0.76604456, 0.0, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [-0.64278764, 0.76604456, 0.0, 1.0],
+    [-0.98480767, 0.17364825, 0.0, 1.0],
+    [-0.64278764, 0.76604456, 0.0, 1.0],
+    [-0.9848078, 0.17364825, 0.0, 1.0],
+    [-0.98480767, 0.17364825, 0.0, 1.0],
+    [0.9848078, -0.17364821, 0.0, -1.0],
+    [0.9848078, -0.17364821, 0.0, -1.0],
+    [0.8660254, 0.50000006, 0.0, -1.0],
+    [0.9848078, -0.17364821, 0.0, -1.0],
+    [0.86602527, 0.5, 0.0, -1.0],
+    [0.8660254, 0.50000006, 0.0, -1.0],
+    [0.0, 1.0, 0.0, 1.0],
+    [-0.07184847, 0.9870102, 0.14369693, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [-0.07184847, 0.9870102, 0.14369693, 1.0],
+    [-0.6427876, 0.7660445, 1.3073846e-8, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [-0.6427875, 0.7660445, 0.0, 1.0],
+    [-0.6427876, 0.7660445, 1.3073846e-8, 1.0],
+    [-0.98480767, 0.17364822, 0.0, 1.0],
+    [-0.6427876, 0.7660445, 1.3073846e-8, 1.0],
+    [-0.9595388, 0.24214949, -0.1436969, 1.0],
+    [-0.98480767, 0.17364822, 0.0, 1.0],
+    [0.98480767, -0.17364818, 0.0, -1.0],
+    [0.9844916, -0.10063562, -0.14369695, -1.0],
+    [0.8660254, 0.50000006, 0.0, -1.0],
+    [0.9844916, -0.10063562, -0.14369695, -1.0],
+    [0.8907001, 0.43128252, 0.14369696, -1.0],
+    [0.8660254, 0.50000006, 0.0, -1.0],
+];
+
+#[test]
+fn generate_tangents_mikktspace() {
+    let mut model = mirrored_strip();
+    model.generate_tangents();
+
+    // the vertices on the mirror line get a copy for the mirrored side
+    assert_eq!(model.vertices[0].len(), 20);
+    assert_eq!(model.normals[0].len(), 20);
+    assert_eq!(model.texcoords.len(), 20);
+
+    for (f, &(a, b, c)) in model.indices.iter().enumerate() {
+        for (i, &v) in [a, b, c].iter().enumerate() {
+            let tangent = model.tangents[0][v];
+            let expected = MIRRORED_STRIP_TANGENTS[f * 3 + i];
+            assert_near(
+                [tangent[0], tangent[1], tangent[2]],
+                [expected[0], expected[1], expected[2]],
+            );
+            assert_eq!(tangent[3], expected[3], "face {} corner {}", f, i);
+        }
+    }
+}
+
+#[test]
+fn tangents_exported() {
+    let mut model = quad(false);
+    let mut json = Vec::new();
+    model.write_json(&mut json).unwrap();
+    assert!(String::from_utf8(json)
+        .unwrap()
+        .contains("\"tangents\": [\n\t\t]"));
+
+    let mut glb = Vec::new();
+    gltf::write_glb(&model, &[], None, &mut glb).unwrap();
+    assert!(!String::from_utf8_lossy(&glb).contains("TANGENT"));
+
+    model.generate_tangents();
+    let mut json = Vec::new();
+    model.write_json(&mut json).unwrap();
+    assert!(String::from_utf8(json)
+        .unwrap()
+        .contains("\"tangents\": [\n\t\t\t1, 0, 0, 1,"));
+
+    let mut glb = Vec::new();
+    gltf::write_glb(&model, &[], None, &mut glb).unwrap();
+    // the base mesh and the morph target
+    assert_eq!(String::from_utf8_lossy(&glb).matches("TANGENT").count(), 2);
+}
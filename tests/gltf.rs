@@ -0,0 +1,163 @@
+extern crate model_reader;
+extern crate serde_json;
+
+use model_reader::animation::Clip;
+use model_reader::flat_model::FlatModel;
+use model_reader::gltf::*;
+use serde_json::Value;
+
+mod common;
+use common::u32_at;
+
+/// a quad (two triangles) with three frames
+fn model() -> FlatModel {
+    let frame = |z: f32| vec![[0.0, 0.0, z], [1.0, 0.0, z], [1.0, 1.0, z], [0.0, 1.0, z]];
+    FlatModel {
+        vertices: vec![frame(0.0), frame(1.0), frame(2.0)],
+        normals: vec![vec![[0.0, 0.0, 1.0]; 4]; 3],
+        tangents: Vec::new(),
+        texcoords: vec![(0.0, 1.0), (1.0, 1.0), (1.0, 0.0), (0.0, 0.0)],
+        indices: vec![(0, 2, 1), (0, 3, 2)],
+    }
+}
+
+/// splits a glb into its json and binary chunk
+fn glb(model: &FlatModel, clips: &[Clip]) -> (Value, Vec<u8>) {
+    let mut glb = Vec::new();
+    write_glb(model, clips, None, &mut glb).unwrap();
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+    let json_len = u32_at(&glb, 12) as usize;
+    let json = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+    let bin_len = u32_at(&glb, 20 + json_len) as usize;
+    let bin_start = 20 + json_len + 8;
+    (json, glb[bin_start..bin_start + bin_len].to_vec())
+}
+
+fn count(json: &Value, accessor: &Value) -> u64 {
+    json["accessors"][accessor.as_u64().unwrap() as usize]["count"]
+        .as_u64()
+        .unwrap()
+}
+
+#[test]
+fn gltf_mesh_and_targets() {
+    let clips = vec![Clip {
+        name: "all".to_string(),
+        first: 0,
+        last: 2,
+        fps: 10.0,
+    }];
+    let (json, bin) = glb(&model(), &clips);
+
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let attributes = &primitive["attributes"];
+    assert_eq!(count(&json, &attributes["POSITION"]), 4);
+    assert_eq!(count(&json, &attributes["NORMAL"]), 4);
+    assert_eq!(count(&json, &attributes["TEXCOORD_0"]), 4);
+    assert_eq!(count(&json, &primitive["indices"]), 6);
+    assert!(attributes.get("TANGENT").is_none());
+
+    // one morph target for every frame after the first
+    let targets = primitive["targets"].as_array().unwrap();
+    assert_eq!(targets.len(), 2);
+    for target in targets {
+        assert_eq!(count(&json, &target["POSITION"]), 4);
+        assert_eq!(count(&json, &target["NORMAL"]), 4);
+    }
+    assert_eq!(json["meshes"][0]["weights"].as_array().unwrap().len(), 2);
+    assert_eq!(json["animations"][0]["name"], "all");
+
+    let buffer_len = json["buffers"][0]["byteLength"].as_u64().unwrap();
+    assert_eq!(buffer_len as usize, bin.len());
+    assert!(json["buffers"][0].get("uri").is_none());
+}
+
+#[test]
+fn gltf_winding_and_orientation() {
+    let (json, bin) = glb(&model(), &[]);
+
+    // indices come first in the buffer, b and c swapped
+    let view = &json["bufferViews"][0];
+    assert_eq!(view["byteOffset"], 0);
+    let indices: Vec<u32> = (0..6).map(|i| u32_at(&bin, i * 4)).collect();
+    assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+
+    let rotation: Vec<f64> = json["nodes"][0]["rotation"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    let half = std::f64::consts::FRAC_1_SQRT_2;
+    assert!((rotation[0] + half).abs() < 1e-6);
+    assert_eq!(rotation[1..3], [0.0, 0.0]);
+    assert!((rotation[3] - half).abs() < 1e-6);
+}
+
+#[test]
+fn gltf_without_normals() {
+    let mut model = model();
+    model.normals.clear();
+    let (json, _) = glb(&model, &[]);
+
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert!(primitive["attributes"].get("NORMAL").is_none());
+    let targets = primitive["targets"].as_array().unwrap();
+    assert_eq!(targets.len(), 2);
+    assert!(targets.iter().all(|target| target.get("NORMAL").is_none()));
+}
+
+#[test]
+fn gltf_invalid_input() {
+    let check = |model: &FlatModel| {
+        let mut glb = Vec::new();
+        let err = write_glb(model, &[], None, &mut glb).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    };
+
+    // fewer normal frames than vertex frames
+    let mut fewer_normals = model();
+    fewer_normals.normals.pop();
+    check(&fewer_normals);
+
+    let mut short_frame = model();
+    short_frame.vertices[1].pop();
+    check(&short_frame);
+
+    let mut tangents = model();
+    tangents.tangents = vec![vec![[1.0, 0.0, 0.0, 1.0]; 4]];
+    check(&tangents);
+
+    let mut out_of_range = model();
+    out_of_range.indices.push((0, 1, 4));
+    check(&out_of_range);
+
+    let mut empty = model();
+    empty.vertices.clear();
+    empty.normals.clear();
+    check(&empty);
+}
+
+#[test]
+fn gltf_files() {
+    let dir = std::env::temp_dir().join(format!("model-reader-gltf-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    write_gltf(&model(), &[], None, &dir, "test").unwrap();
+    let json: Value =
+        serde_json::from_slice(&std::fs::read(dir.join("test.gltf")).unwrap()).unwrap();
+    let bin = std::fs::read(dir.join("test.bin")).unwrap();
+    assert_eq!(json["buffers"][0]["uri"], "test.bin");
+    assert_eq!(json["buffers"][0]["byteLength"], bin.len() as u64);
+    assert_eq!(
+        json["meshes"][0]["primitives"][0]["targets"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
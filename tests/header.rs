@@ -0,0 +1,200 @@
+extern crate model_reader;
+
+use model_reader::{load_any, md2, mdl, AnyModel, ErrorKind, Limits, LoadOptions, Section};
+use std::io::Cursor;
+
+mod common;
+use common::{md2_header, mdl_header, push_i32, set_i32};
+
+#[test]
+fn mdl_header_fields() {
+    let bytes = mdl_header();
+    assert_eq!(bytes.len(), 84);
+
+    let model = mdl::Model::from_reader(&mut Cursor::new(bytes)).unwrap();
+    let header = &model.header;
+    assert_eq!(header.ident, mdl::HEADER_IDENT);
+    assert_eq!(header.version, mdl::HEADER_VERSION);
+    assert_eq!(header.scale, [0.5, 1.5, 2.5]);
+    assert_eq!(header.translate, [-10.0, -20.0, -30.0]);
+    assert_eq!(header.boundigradius, 42.25);
+    assert_eq!(header.eyeposition, [0.0, 0.0, 24.0]);
+    assert_eq!(header.num_skins, 0);
+    assert_eq!(header.skin_width, 296);
+    assert_eq!(header.skin_height, 194);
+    assert_eq!(header.num_verices, 0);
+    assert_eq!(header.num_faces, 0);
+    assert_eq!(header.num_frames, 0);
+    assert_eq!(header.synctype, 1);
+    assert_eq!(header.flags, 8);
+    assert_eq!(header.size, 3.75);
+}
+
+#[test]
+fn mdl_header_bad_ident() {
+    let mut bytes = mdl_header();
+    bytes[0..4].copy_from_slice(b"IDP2");
+    let err = mdl::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::BadIdent {
+            found: md2::HEADER_IDENT,
+            expected: mdl::HEADER_IDENT
+        }
+    );
+    assert_eq!(err.section(), Some(Section::Header));
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[test]
+fn mdl_header_bad_version() {
+    let mut bytes = mdl_header();
+    set_i32(&mut bytes, 4, 7); // version
+    let err = mdl::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::BadVersion {
+            found: 7,
+            expected: mdl::HEADER_VERSION
+        }
+    );
+    assert_eq!(err.offset(), Some(4));
+}
+
+#[test]
+fn mdl_header_truncated() {
+    let mut bytes = mdl_header();
+    bytes.truncate(80);
+    let err = mdl::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(Section::Header));
+    assert_eq!(err.offset(), Some(80)); // size
+}
+
+#[test]
+fn md2_header_fields() {
+    let bytes = md2_header();
+    assert_eq!(bytes.len(), 68);
+
+    let model = md2::Model::from_reader(&mut Cursor::new(bytes)).unwrap();
+    let header = &model.header;
+    assert_eq!(header.ident, md2::HEADER_IDENT);
+    assert_eq!(header.version, md2::HEADER_VERSION);
+    assert_eq!(header.skin_width, 256);
+    assert_eq!(header.skin_height, 128);
+    assert_eq!(header.frame_size, 40);
+    assert_eq!(header.num_skins, 0);
+    assert_eq!(header.num_vertices, 0);
+    assert_eq!(header.num_texcoords, 0);
+    assert_eq!(header.num_faces, 0);
+    assert_eq!(header.num_gl_cmds, 0);
+    assert_eq!(header.num_frames, 0);
+    assert_eq!(header.offset_skins, 68);
+    assert_eq!(header.offset_texcoords, 132);
+    assert_eq!(header.offset_faces, 196);
+    assert_eq!(header.offset_frames, 260);
+    assert_eq!(header.offset_gl_cmds, 324);
+    assert_eq!(header.offset_end, 388);
+}
+
+#[test]
+fn md2_header_bad_ident() {
+    let mut bytes = md2_header();
+    bytes[0..4].copy_from_slice(b"IDPO");
+    assert!(md2::Model::from_reader(&mut Cursor::new(bytes)).is_err());
+}
+
+#[test]
+fn md2_header_truncated() {
+    let mut bytes = md2_header();
+    bytes.truncate(60);
+    let err = md2::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.offset(), Some(60)); // offset_gl_cmds
+}
+
+#[test]
+fn load_any_detects_format() {
+    match load_any(&mut Cursor::new(mdl_header())).unwrap() {
+        AnyModel::Mdl(model) => assert_eq!(model.header.skin_width, 296),
+        _ => panic!("expected mdl"),
+    }
+    match load_any(&mut Cursor::new(md2_header())).unwrap() {
+        AnyModel::Md2(model) => assert_eq!(model.header.skin_width, 256),
+        _ => panic!("expected md2"),
+    }
+}
+
+#[test]
+fn load_any_unknown_magic() {
+    let err = load_any(&mut Cursor::new(b"RIFF\0\0\0\0".to_vec()))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), &ErrorKind::UnknownFormat { magic: *b"RIFF" });
+    assert!(err.to_string().contains("'RIFF'"));
+}
+
+#[test]
+fn mdl_skin_limit() {
+    let mut bytes = mdl_header();
+    set_i32(&mut bytes, 48, 1); // num_skins
+    set_i32(&mut bytes, 52, 100_000); // skin_width
+    set_i32(&mut bytes, 56, 100_000); // skin_height
+    push_i32(&mut bytes, 0); // skin group
+
+    let err = mdl::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::LimitExceeded {
+            value: 100_000 * 100_000,
+            limit: Limits::default().max_skin_pixels
+        }
+    );
+    assert_eq!(err.section(), Some(Section::Skins));
+}
+
+#[test]
+fn md2_frame_limit() {
+    let mut bytes = md2_header();
+    set_i32(&mut bytes, 40, 100); // num_frames
+    let options = LoadOptions {
+        limits: Limits {
+            max_frames: 10,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let err = md2::Model::from_reader_with(&mut Cursor::new(bytes), &options)
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::LimitExceeded {
+            value: 100,
+            limit: 10
+        }
+    );
+    assert_eq!(err.section(), Some(Section::Frames));
+}
+
+#[test]
+fn md2_negative_count() {
+    let mut bytes = md2_header();
+    set_i32(&mut bytes, 32, -1); // num_faces
+    let err = md2::Model::from_reader(&mut Cursor::new(bytes))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Unsupported);
+    assert_eq!(err.section(), Some(Section::Triangles));
+}
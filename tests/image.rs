@@ -0,0 +1,101 @@
+extern crate model_reader;
+
+use model_reader::image::{Image, FULLBRIGHT_START};
+use model_reader::mdl;
+use model_reader::palette::Palette;
+use std::io::Cursor;
+
+mod common;
+use common::{mdl_header, mdl_model, push_i32, set_i32};
+
+fn rgba(palette: &Palette, idx: u8) -> Vec<u8> {
+    let rgb = palette.color(idx);
+    vec![rgb[0], rgb[1], rgb[2], 255]
+}
+
+/// mdl with one simple 4x2 skin and nothing else
+fn mdl_with_skin(pixels: &[u8]) -> Vec<u8> {
+    let mut buf = mdl_header();
+    set_i32(&mut buf, 48, 1); // num_skins
+    set_i32(&mut buf, 52, 4); // skin_width
+    set_i32(&mut buf, 56, 2); // skin_height
+    push_i32(&mut buf, 0); // simple skin
+    buf.extend_from_slice(pixels);
+    buf
+}
+
+#[test]
+fn indexed_to_rgba() {
+    let palette = Palette::default();
+    let indices = [0, 1, 2, 3, 4, 5];
+    let (color, emissive) = Image::from_indexed(&indices, 3, 2, &palette, false);
+    assert!(emissive.is_none());
+    assert_eq!((color.width, color.height), (3, 2));
+    assert_eq!(color.data.len(), 3 * 2 * 4);
+    for (i, idx) in indices.iter().enumerate() {
+        assert_eq!(color.data[i * 4..i * 4 + 4], rgba(&palette, *idx)[..]);
+    }
+}
+
+#[test]
+fn indexed_short_input_is_padded() {
+    let palette = Palette::default();
+    let (color, emissive) = Image::from_indexed(&[7, 8], 2, 2, &palette, true);
+    assert_eq!(color.data.len(), 2 * 2 * 4);
+    assert_eq!(color.data[0..4], rgba(&palette, 7)[..]);
+    assert_eq!(color.data[8..16], [0; 8]);
+    assert_eq!(emissive.unwrap().data.len(), 2 * 2 * 4);
+}
+
+#[test]
+fn fullbright_split() {
+    let palette = Palette::default();
+    let indices = [0, FULLBRIGHT_START - 1, FULLBRIGHT_START, 255];
+    let (color, emissive) = Image::from_indexed(&indices, 2, 2, &palette, true);
+    let emissive = emissive.unwrap();
+
+    // below 224 the pixel stays in the color image
+    assert_eq!(color.data[0..4], rgba(&palette, 0)[..]);
+    assert_eq!(color.data[4..8], rgba(&palette, 223)[..]);
+    assert_eq!(emissive.data[0..8], [0; 8]);
+
+    // from 224 on it moves to the mask and is black in the color image
+    assert_eq!(color.data[8..16], [0, 0, 0, 255, 0, 0, 0, 255]);
+    assert_eq!(emissive.data[8..12], rgba(&palette, 224)[..]);
+    assert_eq!(emissive.data[12..16], rgba(&palette, 255)[..]);
+}
+
+#[test]
+fn mdl_skin_rgba() {
+    let pixels = [0, 1, 2, 3, 224, 225, 254, 255];
+    let bytes = mdl_with_skin(&pixels);
+    let model = mdl::Model::from_reader(&mut Cursor::new(bytes)).unwrap();
+    let palette = Palette::default();
+
+    let (color, emissive) = model.skin_rgba(0, 0, &palette, true).unwrap();
+    let emissive = emissive.unwrap();
+    assert_eq!((color.width, color.height), (4, 2));
+    assert_eq!(color.data[12..16], rgba(&palette, 3)[..]);
+    assert_eq!(color.data[16..20], [0, 0, 0, 255]);
+    assert_eq!(emissive.data[16..20], rgba(&palette, 224)[..]);
+    assert_eq!(emissive.data[12..16], [0; 4]);
+
+    assert!(model.skin_rgba(0, 1, &palette, false).is_none());
+    assert!(model.skin_rgba(1, 0, &palette, false).is_none());
+}
+
+#[test]
+fn mdl_group_skin_rgba() {
+    // skin 1 is a group of two 4x2 images with the pixels 0 .. 15
+    let model = mdl_model();
+    let palette = Palette::default();
+
+    let (color, _) = model.skin_rgba(1, 1, &palette, false).unwrap();
+    assert_eq!(color.data[0..4], rgba(&palette, 8)[..]);
+    assert!(model.skin_rgba(1, 2, &palette, false).is_none());
+    // the offset of the image would overflow
+    assert!(model
+        .skin_rgba(1, usize::MAX / 4, &palette, false)
+        .is_none());
+    assert!(model.skin_rgba(1, usize::MAX, &palette, false).is_none());
+}
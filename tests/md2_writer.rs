@@ -0,0 +1,184 @@
+extern crate model_reader;
+
+use model_reader::flat_model::FlatModel;
+use model_reader::md2::*;
+use model_reader::validation::Issue;
+use model_reader::{ErrorKind, LoadOptions, NORMALS};
+use std::io::Cursor;
+
+mod common;
+use common::md2_model;
+
+#[test]
+fn md2_write_header() {
+    let mut cursor = Cursor::new(Vec::new());
+    md2_model().write_to(&mut cursor).unwrap();
+    let bytes = cursor.into_inner();
+
+    let model = Model::from_reader(&mut Cursor::new(&bytes)).unwrap();
+    let header = &model.header;
+    assert_eq!(header.skin_width, 64);
+    assert_eq!(header.skin_height, 32);
+    assert_eq!(header.frame_size, 40 + 4 * 3);
+    assert_eq!(header.num_skins, 1);
+    assert_eq!(header.num_vertices, 3);
+    assert_eq!(header.num_texcoords, 3);
+    assert_eq!(header.num_faces, 1);
+    assert_eq!(header.num_gl_cmds, 2 * (1 + 3 * 3) + 1);
+    assert_eq!(header.num_frames, 2);
+    assert_eq!(header.offset_skins, 68);
+    assert_eq!(header.offset_texcoords, 68 + 64);
+    assert_eq!(header.offset_faces, 68 + 64 + 3 * 4);
+    assert_eq!(header.offset_frames, 68 + 64 + 3 * 4 + 12);
+    assert_eq!(header.offset_gl_cmds, 68 + 64 + 3 * 4 + 12 + 2 * 52);
+    assert_eq!(header.offset_end as usize, bytes.len());
+}
+
+#[test]
+fn md2_write_read() {
+    let mut cursor = Cursor::new(Vec::new());
+    md2_model().write_to(&mut cursor).unwrap();
+    let model = Model::from_reader(&mut Cursor::new(cursor.into_inner())).unwrap();
+
+    assert_eq!(model.skin_names, vec!["models/test/skin.pcx".to_string()]);
+    assert_eq!(model.texcoords[1].s, 63);
+    assert_eq!(model.texcoords[2].t, 31);
+    assert_eq!(model.faces[0].vertex, [0, 1, 2]);
+    assert_eq!(model.faces[0].st_idx, [0, 1, 2]);
+    assert_eq!(model.frames[1].name, "stand02");
+    assert_eq!(model.frames[1].scale, [0.5, 0.25, 2.0]);
+    assert_eq!(model.frames[1].translate, [-8.0, 4.0, 16.0]);
+    assert_eq!(model.frames[1].vertices[1].v, [255, 0, 10]);
+    assert_eq!(model.frames[1].vertices[2].normal_idx, 161);
+    assert_eq!(model.commands.len(), 2);
+    assert_eq!(model.commands[0].typ, CommandType::Strip);
+    assert_eq!(model.commands[1].typ, CommandType::Fan);
+    assert_eq!(model.commands[1].packets[0].i, 2);
+    assert_eq!(model.commands[1].packets[1].s, 1.0);
+}
+
+#[test]
+fn md2_round_trip_identical_bytes() {
+    let mut cursor = Cursor::new(Vec::new());
+    md2_model().write_to(&mut cursor).unwrap();
+    let first = cursor.into_inner();
+
+    let model = Model::from_reader(&mut Cursor::new(&first)).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    model.write_to(&mut cursor).unwrap();
+    let second = cursor.into_inner();
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn md2_round_trip_name_tail() {
+    let mut cursor = Cursor::new(Vec::new());
+    md2_model().write_to(&mut cursor).unwrap();
+    let clean = cursor.into_inner();
+
+    // garbage after the terminating zero of the skin
+    // name and of the first frame name
+    let mut dirty = clean.clone();
+    let skin_tail = 68 + "models/test/skin.pcx".len() + 1;
+    dirty[skin_tail..skin_tail + 3].copy_from_slice(b"xyz");
+    let frame_tail = 68 + 64 + 3 * 4 + 12 + 24 + "stand01".len() + 1;
+    dirty[frame_tail..frame_tail + 2].copy_from_slice(&[0xcd, 0xcd]);
+
+    let model = Model::from_reader(&mut Cursor::new(&dirty)).unwrap();
+    assert_eq!(model.skin_names[0], "models/test/skin.pcx");
+    assert_eq!(model.frames[0].name, "stand01");
+
+    // the tails are not kept, the names are written zero padded
+    let mut cursor = Cursor::new(Vec::new());
+    model.write_to(&mut cursor).unwrap();
+    let written = cursor.into_inner();
+    assert_ne!(written, dirty);
+    assert_eq!(written, clean);
+}
+
+#[test]
+fn md2_write_long_name() {
+    let mut model = md2_model();
+    model.frames[0].name = "a_frame_name_longer_than_16".to_string();
+    assert!(model.write_to(&mut Cursor::new(Vec::new())).is_err());
+}
+
+#[test]
+fn md2_validate() {
+    let mut model = md2_model();
+    assert!(model.validate().is_empty());
+
+    model.faces[0].vertex[1] = 3;
+    model.faces[0].st_idx[2] = 7;
+    model.commands[0].packets[0].i = -1;
+    assert_eq!(
+        model.validate(),
+        vec![
+            Issue::VertexIndex {
+                triangle: 0,
+                index: 3,
+                len: 3
+            },
+            Issue::TexcoordIndex {
+                triangle: 0,
+                index: 7,
+                len: 3
+            },
+            Issue::CommandIndex {
+                command: 0,
+                index: -1,
+                len: 3
+            },
+        ]
+    );
+}
+
+#[test]
+fn md2_strict_load() {
+    let mut model = md2_model();
+    model.faces[0].vertex[0] = 100;
+    let mut cursor = Cursor::new(Vec::new());
+    model.write_to(&mut cursor).unwrap();
+    let bytes = cursor.into_inner();
+
+    assert!(Model::from_reader(&mut Cursor::new(&bytes)).is_ok());
+
+    let options = LoadOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let err = Model::from_reader_with(&mut Cursor::new(&bytes), &options)
+        .err()
+        .unwrap();
+    match err.kind() {
+        ErrorKind::Invalid { issues } => assert_eq!(issues.len(), 1),
+        _ => panic!("expected invalid"),
+    }
+}
+
+#[test]
+fn md2_flat_normals() {
+    let mut model = md2_model();
+    // vertex 2 used with the texcoord of vertex 1 gets a copy
+    model.faces.push(Triangle {
+        vertex: [1, 0, 2],
+        st_idx: [1, 0, 1],
+    });
+    let flat = FlatModel::from_md2(&model);
+
+    assert_eq!(flat.indices, vec![(0, 1, 2), (1, 0, 3)]);
+    assert_eq!(flat.normals.len(), 2);
+    for frame in &flat.normals {
+        assert_eq!(
+            frame,
+            &vec![NORMALS[5], NORMALS[6], NORMALS[161], NORMALS[161]]
+        );
+    }
+
+    let mut json = Vec::new();
+    flat.write_json(&mut json).unwrap();
+    let [x, y, z] = NORMALS[161];
+    let copy = format!("{}, {}, {}\n\t\t],\n\t\t\"tangents\"", x, y, z);
+    assert_eq!(String::from_utf8(json).unwrap().matches(&copy).count(), 2);
+}
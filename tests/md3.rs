@@ -0,0 +1,168 @@
+extern crate model_reader;
+
+use model_reader::md3::*;
+use model_reader::{AliasModel, ErrorKind, Section};
+use std::io::Cursor;
+
+mod common;
+use common::{assert_near_eps, push_f32, push_i16, push_i32, push_name, push_vec3};
+
+/// packs latitude and longitude like md3 vertices do
+fn normal(lat: u8, lng: u8) -> i16 {
+    ((lat as u16) << 8 | lng as u16) as i16
+}
+
+const OFFSET_FRAMES: i32 = 108;
+const OFFSET_TAGS: i32 = OFFSET_FRAMES + 2 * 56;
+// surfaces start after 4 bytes of padding, so offsets
+// that are taken as absolute instead of relative fail
+const OFFSET_SURFACES: i32 = OFFSET_TAGS + 2 * 112 + 4;
+const SURFACE_SIZE: i32 = 260;
+
+/// two frames, one tag and one surface with one shader,
+/// one triangle and three vertices
+fn md3() -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_i32(&mut buf, HEADER_IDENT);
+    push_i32(&mut buf, HEADER_VERSION);
+    push_name(&mut buf, "models/test/head.md3", 64);
+    push_i32(&mut buf, 7); // flags
+    push_i32(&mut buf, 2); // num_frames
+    push_i32(&mut buf, 1); // num_tags
+    push_i32(&mut buf, 1); // num_surfaces
+    push_i32(&mut buf, 0); // num_skins
+    push_i32(&mut buf, OFFSET_FRAMES);
+    push_i32(&mut buf, OFFSET_TAGS);
+    push_i32(&mut buf, OFFSET_SURFACES);
+    push_i32(&mut buf, OFFSET_SURFACES + SURFACE_SIZE); // offset_end
+    assert_eq!(buf.len(), OFFSET_FRAMES as usize);
+
+    for (i, name) in ["idle", "nod"].iter().enumerate() {
+        let i = i as f32;
+        push_vec3(&mut buf, [-1.0 - i, -2.0, -3.0]); // min_bounds
+        push_vec3(&mut buf, [1.0 + i, 2.0, 3.0]); // max_bounds
+        push_vec3(&mut buf, [0.0, 0.0, i]); // local_origin
+        push_f32(&mut buf, 4.5 + i); // radius
+        push_name(&mut buf, name, 16);
+    }
+    assert_eq!(buf.len(), OFFSET_TAGS as usize);
+
+    // one tag for each frame
+    for i in 0..2 {
+        push_name(&mut buf, "tag_head", 64);
+        push_vec3(&mut buf, [0.0, 0.0, 10.0 + i as f32]); // origin
+        push_vec3(&mut buf, [1.0, 0.0, 0.0]);
+        push_vec3(&mut buf, [0.0, 1.0, 0.0]);
+        push_vec3(&mut buf, [0.0, 0.0, 1.0]);
+    }
+    buf.extend_from_slice(&[0xff; 4]);
+    assert_eq!(buf.len(), OFFSET_SURFACES as usize);
+
+    // surface header, offsets relative to its start
+    push_i32(&mut buf, HEADER_IDENT);
+    push_name(&mut buf, "h_head", 64);
+    push_i32(&mut buf, 0); // flags
+    push_i32(&mut buf, 2); // num_frames
+    push_i32(&mut buf, 1); // num_shaders
+    push_i32(&mut buf, 3); // num_vertices
+    push_i32(&mut buf, 1); // num_triangles
+    push_i32(&mut buf, 176); // offset_triangles
+    push_i32(&mut buf, 108); // offset_shaders
+    push_i32(&mut buf, 188); // offset_st
+    push_i32(&mut buf, 212); // offset_xyznormal
+    push_i32(&mut buf, SURFACE_SIZE); // offset_end
+
+    push_name(&mut buf, "models/test/head.tga", 64);
+    push_i32(&mut buf, 3); // shader_idx
+
+    for v in &[0, 2, 1] {
+        push_i32(&mut buf, *v);
+    }
+
+    for (s, t) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 0.5)] {
+        push_f32(&mut buf, *s);
+        push_f32(&mut buf, *t);
+    }
+
+    for frame in 0..2 {
+        for (v, n) in &[
+            ([64, -128, 32], normal(0, 0)),
+            ([0, 64, 0], normal(64, 64)),
+            ([-64, 0, 640], normal(0, 128)),
+        ] {
+            push_i16(&mut buf, v[0]);
+            push_i16(&mut buf, v[1]);
+            push_i16(&mut buf, v[2] + frame * 64);
+            push_i16(&mut buf, *n);
+        }
+    }
+    assert_eq!(buf.len(), (OFFSET_SURFACES + SURFACE_SIZE) as usize);
+    buf
+}
+
+#[test]
+fn md3_header_and_frames() {
+    let model = Model::from_reader(&mut Cursor::new(md3())).unwrap();
+    let header = &model.header;
+    assert_eq!(header.ident, HEADER_IDENT);
+    assert_eq!(header.version, HEADER_VERSION);
+    assert_eq!(header.name, "models/test/head.md3");
+    assert_eq!(header.flags, 7);
+    assert_eq!(header.num_frames, 2);
+    assert_eq!(header.num_tags, 1);
+    assert_eq!(header.num_surfaces, 1);
+    assert_eq!(header.offset_end, OFFSET_SURFACES + SURFACE_SIZE);
+
+    assert_eq!(model.frames.len(), 2);
+    let frame = &model.frames[1];
+    assert_eq!(frame.name, "nod");
+    assert_eq!(frame.min_bounds, [-2.0, -2.0, -3.0]);
+    assert_eq!(frame.max_bounds, [2.0, 2.0, 3.0]);
+    assert_eq!(frame.local_origin, [0.0, 0.0, 1.0]);
+    assert_eq!(frame.radius, 5.5);
+    assert_eq!(model.frame_names(), vec!["idle", "nod"]);
+
+    assert_eq!(model.tags.len(), 2);
+    assert!(model.tags.iter().all(|tag| tag.name == "tag_head"));
+    assert_eq!(model.tags[1].origin, [0.0, 0.0, 11.0]);
+    assert_eq!(model.tags[1].axis[1], [0.0, 1.0, 0.0]);
+    assert!(model.validate().is_empty());
+}
+
+#[test]
+fn md3_surfaces() {
+    let model = Model::from_reader(&mut Cursor::new(md3())).unwrap();
+    assert_eq!(model.surfaces.len(), 1);
+    let surface = &model.surfaces[0];
+    assert_eq!(surface.header.name, "h_head");
+    assert_eq!(surface.header.num_vertices, 3);
+
+    assert_eq!(surface.shaders.len(), 1);
+    assert_eq!(surface.shaders[0].name, "models/test/head.tga");
+    assert_eq!(surface.shaders[0].shader_idx, 3);
+
+    assert_eq!(surface.triangles.len(), 1);
+    assert_eq!(surface.triangles[0].vertex, [0, 2, 1]);
+    assert_eq!(surface.texcoords[2].s, 0.0);
+    assert_eq!(surface.texcoords[2].t, 0.5);
+
+    assert_eq!(surface.vertices.len(), 2);
+    let vertices = &surface.vertices[1];
+    assert_eq!(vertices[0].position(), [1.0, -2.0, 1.5]);
+    assert_eq!(vertices[2].position(), [-1.0, 0.0, 11.0]);
+
+    // latitude and longitude of 0, 90 and 180 degrees, packed as 0..255
+    assert_near_eps(vertices[0].normal(), [0.0, 0.0, 1.0], 1e-6);
+    assert_near_eps(vertices[1].normal(), [0.0, 1.0, 0.0], 0.02);
+    assert_near_eps(vertices[2].normal(), [0.0, 0.0, -1.0], 0.02);
+    assert_eq!(model.normals(1)[1], vertices[1].normal());
+}
+
+#[test]
+fn md3_truncated_surface() {
+    let mut bytes = md3();
+    bytes.truncate(bytes.len() - 4);
+    let err = Model::from_reader(&mut Cursor::new(bytes)).err().unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(Section::Surfaces));
+}
@@ -0,0 +1,185 @@
+extern crate model_reader;
+
+use model_reader::flat_model::FlatModel;
+use model_reader::mdl::*;
+use model_reader::validation::Issue;
+use model_reader::NORMALS;
+use std::io::Cursor;
+
+mod common;
+use common::{mdl_frame, mdl_model, mdl_vertex};
+
+fn write(model: &Model) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    model.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn mdl_write_size() {
+    let bytes = write(&mdl_model());
+    let skins = (4 + 8) + (4 + 4 + 2 * 4 + 16);
+    let texcoords = 3 * 12;
+    let triangles = 2 * 16;
+    let simple_frame = 4 + 4 + 16 + 3 * 4;
+    let frames = (4 + simple_frame) + (4 + 4 + 4 + 4 + 3 * 4 + 3 * simple_frame);
+    assert_eq!(bytes.len(), 84 + skins + texcoords + triangles + frames);
+}
+
+#[test]
+fn mdl_write_read() {
+    let bytes = write(&mdl_model());
+    let model = Model::from_reader(&mut Cursor::new(bytes)).unwrap();
+
+    let header = &model.header;
+    assert_eq!(header.scale, [0.5, 0.25, 2.0]);
+    assert_eq!(header.translate, [-8.0, 4.0, 16.0]);
+    assert_eq!(header.boundigradius, 30.5);
+    assert_eq!(header.eyeposition, [0.0, 0.0, 22.0]);
+    assert_eq!(header.num_skins, 2);
+    assert_eq!(header.skin_width, 4);
+    assert_eq!(header.skin_height, 2);
+    assert_eq!(header.num_verices, 3);
+    assert_eq!(header.num_faces, 2);
+    assert_eq!(header.num_frames, 2);
+    assert_eq!(header.synctype, 1);
+    assert_eq!(header.flags, 4);
+    assert_eq!(header.size, 7.5);
+
+    match &model.skins[0] {
+        Skin::Simple(skin) => assert_eq!(skin.data, vec![0, 1, 2, 3, 224, 225, 254, 255]),
+        Skin::Group(_) => panic!("expected simple skin"),
+    }
+    match &model.skins[1] {
+        Skin::Group(skin) => {
+            assert_eq!(skin.nb, 2);
+            assert_eq!(skin.time, vec![0.1, 0.2]);
+            assert_eq!(skin.data, (0..16).collect::<Vec<u8>>());
+        }
+        Skin::Simple(_) => panic!("expected group skin"),
+    }
+
+    assert_eq!(model.texcoords[1].onseam, 32);
+    assert_eq!(model.texcoords[1].s, 3);
+    assert_eq!(model.texcoords[2].t, 1);
+    assert_eq!(model.triangles[1].facefront, 0);
+    assert_eq!(model.triangles[1].vertex, [2, 1, 0]);
+
+    match &model.frames[0] {
+        Frame::Simple(frame) => {
+            assert_eq!(frame.name, "stand1");
+            assert_eq!(frame.verts[1].v, [11, 244, 5]);
+            assert_eq!(frame.verts[2].normal_idx, 3);
+        }
+        Frame::Group(_) => panic!("expected simple frame"),
+    }
+    match &model.frames[1] {
+        Frame::Group(group) => {
+            assert_eq!(group.max.v, [200, 55, 100]);
+            assert_eq!(group.time, vec![0.1, 0.2, 0.3]);
+            assert_eq!(group.frames.len(), 3);
+            assert_eq!(group.frames[2].name, "flame3");
+            assert_eq!(group.frames[2].verts[0].v, [40, 215, 20]);
+        }
+        Frame::Simple(_) => panic!("expected group frame"),
+    }
+}
+
+#[test]
+fn mdl_round_trip_identical_bytes() {
+    let first = write(&mdl_model());
+    let model = Model::from_reader(&mut Cursor::new(&first)).unwrap();
+    let second = write(&model);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn mdl_write_long_frame_name() {
+    let mut model = mdl_model();
+    model.frames[0] = Frame::Simple(mdl_frame("a_frame_name_longer_than_16", 0));
+    assert!(model.write_to(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn mdl_write_group_times() {
+    let mut model = mdl_model();
+    if let Frame::Group(group) = &mut model.frames[1] {
+        group.time.pop();
+    }
+    assert_eq!(
+        model.validate(),
+        vec![Issue::GroupTimes {
+            frame: 1,
+            count: 2,
+            expected: 3
+        }]
+    );
+    let err = model.write_to(&mut Vec::new()).err().unwrap();
+    assert_eq!(err.kind(), &model_reader::ErrorKind::Unsupported);
+}
+
+#[test]
+fn mdl_truncated_frames() {
+    let mut bytes = write(&mdl_model());
+    let len = bytes.len();
+    bytes.truncate(len - 2);
+    let err = Model::from_reader(&mut Cursor::new(bytes)).err().unwrap();
+    assert_eq!(err.kind(), &model_reader::ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(model_reader::Section::Frames));
+    assert_eq!(err.offset(), Some(len as u64 - 4)); // last vertex
+}
+
+#[test]
+fn mdl_validate() {
+    let mut model = mdl_model();
+    assert!(model.validate().is_empty());
+
+    model.triangles[1].vertex[0] = 5;
+    model.frames[0] = Frame::Group(GroupFrame {
+        min: mdl_vertex(0, 0),
+        max: mdl_vertex(0, 0),
+        time: Vec::new(),
+        frames: Vec::new(),
+    });
+    let issues = model.validate();
+    assert_eq!(
+        issues,
+        vec![
+            Issue::VertexIndex {
+                triangle: 1,
+                index: 5,
+                len: 3
+            },
+            Issue::EmptyGroup { frame: 0 },
+        ]
+    );
+}
+
+#[test]
+fn mdl_flat_normals() {
+    let flat = FlatModel::from_mdl(&mdl_model());
+
+    // vertex 1 is on the seam and used by the back face,
+    // its copy for the back half of the skin is vertex 3
+    assert_eq!(flat.vertices[0].len(), 4);
+    assert_eq!(flat.normals.len(), 4);
+    for frame in &flat.normals {
+        assert_eq!(frame[0], NORMALS[1]);
+        assert_eq!(frame[1], NORMALS[2]);
+        assert_eq!(frame[2], NORMALS[3]);
+        assert_eq!(frame[3], NORMALS[2]);
+    }
+
+    let mut json = Vec::new();
+    flat.write_json(&mut json).unwrap();
+    let normals: Vec<String> = [1, 2, 3, 2]
+        .iter()
+        .map(|&idx| {
+            let [x, y, z] = NORMALS[idx];
+            format!("\n\t\t\t{}, {}, {}", x, y, z)
+        })
+        .collect();
+    let expected = format!("\"normals\": [{}\n\t\t]", normals.join(","));
+    let json = String::from_utf8(json).unwrap();
+    assert_eq!(json.matches(&expected).count(), 4);
+}
@@ -0,0 +1,107 @@
+extern crate model_reader;
+
+use model_reader::flat_model::FlatModel;
+use model_reader::image::Image;
+use model_reader::obj::*;
+
+/// one triangle, two frames
+fn model() -> FlatModel {
+    FlatModel {
+        vertices: vec![
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            vec![[0.0, 0.0, 2.0], [1.0, 0.0, 2.0], [0.0, 1.0, 2.0]],
+        ],
+        normals: vec![vec![[0.0, 0.0, -1.0]; 3], vec![[0.0, 0.0, -1.0]; 3]],
+        tangents: Vec::new(),
+        texcoords: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 0.25)],
+        indices: vec![(0, 1, 2)],
+    }
+}
+
+fn obj(model: &FlatModel, frame_idx: usize, mtl_name: Option<&str>) -> String {
+    let mut out = Vec::new();
+    write_obj(model, frame_idx, mtl_name, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn obj_frame() {
+    let obj = obj(&model(), 1, Some("test.mtl"));
+    let lines: Vec<&str> = obj.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "mtllib test.mtl",
+            "v 0 0 2",
+            "v 1 0 2",
+            "v 0 1 2",
+            "vt 0 1",
+            "vt 1 1",
+            "vt 0 0.75",
+            "vn 0 0 -1",
+            "vn 0 0 -1",
+            "vn 0 0 -1",
+            "usemtl skin",
+            // quake winds clockwise, obj counter clockwise
+            "f 1/1/1 3/3/3 2/2/2",
+        ]
+    );
+}
+
+#[test]
+fn obj_without_normals() {
+    let mut model = model();
+    model.normals.clear();
+    let obj = obj(&model, 0, None);
+    assert!(!obj.contains("vn "));
+    assert!(!obj.contains("mtllib"));
+    assert!(!obj.contains("usemtl"));
+    assert!(obj.ends_with("f 1/1 3/3 2/2\n"));
+
+    let mut out = Vec::new();
+    let err = write_obj(&model, 2, None, &mut out).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn mtl() {
+    let mut out = Vec::new();
+    write_mtl(Some("test.png"), &mut out).unwrap();
+    let mtl = String::from_utf8(out).unwrap();
+    assert!(mtl.starts_with("newmtl skin\n"));
+    assert!(mtl.ends_with("map_Kd test.png\n"));
+
+    let mut out = Vec::new();
+    write_mtl(None, &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("map_Kd"));
+}
+
+#[test]
+fn export_files() {
+    let dir = std::env::temp_dir().join(format!("model-reader-obj-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let skin = Image {
+        width: 1,
+        height: 1,
+        data: vec![255, 0, 0, 255],
+    };
+
+    let model = model();
+    export(&model, Frames::All, Some(&skin), &dir, "test").unwrap();
+    let mtl = std::fs::read_to_string(dir.join("test.mtl")).unwrap();
+    assert!(mtl.contains("map_Kd test.png\n"));
+    let png = std::fs::read(dir.join("test.png")).unwrap();
+    assert_eq!(png[1..4], *b"PNG");
+    for frame_idx in 0..2 {
+        let path = dir.join(format!("test_{:03}.obj", frame_idx));
+        let written = std::fs::read_to_string(path).unwrap();
+        assert_eq!(written, obj(&model, frame_idx, Some("test.mtl")));
+    }
+
+    export(&model, Frames::One(1), None, &dir, "single").unwrap();
+    let written = std::fs::read_to_string(dir.join("single.obj")).unwrap();
+    assert_eq!(written, obj(&model, 1, Some("single.mtl")));
+    assert!(!dir.join("single.png").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,36 @@
+extern crate model_reader;
+
+use model_reader::palette::Palette;
+use model_reader::{ErrorKind, Section};
+use std::io::Cursor;
+
+/// palette.lmp where color i is (i, 255 - i, i / 2)
+fn lmp() -> Vec<u8> {
+    let mut buf = Vec::new();
+    for i in 0..=255u8 {
+        buf.extend_from_slice(&[i, 255 - i, i / 2]);
+    }
+    buf
+}
+
+#[test]
+fn palette_from_lmp() {
+    let bytes = lmp();
+    assert_eq!(bytes.len(), 768);
+
+    let palette = Palette::from_reader(&mut Cursor::new(bytes)).unwrap();
+    assert_eq!(palette.color(0), [0, 255, 0]);
+    assert_eq!(palette.color(1), [1, 254, 0]);
+    assert_eq!(palette.color(224), [224, 31, 112]);
+    assert_eq!(palette.color(255), [255, 0, 127]);
+}
+
+#[test]
+fn palette_truncated() {
+    let mut bytes = lmp();
+    bytes.truncate(767);
+    let err = Palette::from_reader(&mut Cursor::new(bytes)).err().unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(Section::Palette));
+    assert_eq!(err.offset(), Some(765)); // last color
+}
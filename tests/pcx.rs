@@ -0,0 +1,100 @@
+extern crate model_reader;
+
+use model_reader::md2;
+use model_reader::pcx::*;
+use model_reader::{ErrorKind, Section};
+use std::io::Cursor;
+
+mod common;
+use common::{md2_header, push_name, push_u16, set_i32};
+
+/// 3x2 pcx with 4 bytes per line. color i of the palette is (i, i, i)
+fn pcx(rle: &[u8]) -> Vec<u8> {
+    let mut buf = vec![HEADER_MANUFACTURER, 5, 1, 8];
+    for v in &[0, 0, 2, 1, 72, 72] {
+        push_u16(&mut buf, *v); // xmin .. vres
+    }
+    buf.extend_from_slice(&[0; 48]); // 16 color palette
+    buf.extend_from_slice(&[0, 1]); // reserved, color_planes
+    push_u16(&mut buf, 4); // bytes_per_line
+    push_u16(&mut buf, 1); // palette_type
+    buf.extend_from_slice(&[0; 58]);
+    assert_eq!(buf.len(), 128);
+
+    buf.extend_from_slice(rle);
+    buf.push(PALETTE_MARKER);
+    for i in 0..=255u8 {
+        buf.extend_from_slice(&[i, i, i]);
+    }
+    buf
+}
+
+#[test]
+fn pcx_rle() {
+    // run of two, empty run, value with the top bits set,
+    // padding byte, run of three, padding byte
+    let rle = [0xc2, 7, 0xc0, 99, 0xc1, 0xc5, 9, 0xc3, 3, 1];
+    let pcx = Pcx::from_reader(&mut Cursor::new(pcx(&rle))).unwrap();
+    assert_eq!((pcx.width(), pcx.height()), (3, 2));
+    assert_eq!(pcx.header.bytes_per_line, 4);
+    assert_eq!(pcx.data, vec![7, 7, 0xc5, 3, 3, 3]);
+    assert_eq!(pcx.palette.color(0xc5), [0xc5, 0xc5, 0xc5]);
+
+    let image = pcx.to_rgba();
+    assert_eq!(image.data.len(), 3 * 2 * 4);
+    assert_eq!(image.data[8..12], [0xc5, 0xc5, 0xc5, 255]);
+}
+
+#[test]
+fn pcx_run_across_lines() {
+    // the run of five fills line 0 with its padding byte
+    // and continues on line 1
+    let rle = [0xc5, 4, 0xc3, 6];
+    let pcx = Pcx::from_reader(&mut Cursor::new(pcx(&rle))).unwrap();
+    assert_eq!(pcx.data, vec![4, 4, 4, 4, 6, 6]);
+}
+
+#[test]
+fn pcx_truncated_data() {
+    let mut bytes = pcx(&[]);
+    bytes.truncate(130);
+    let err = Pcx::from_reader(&mut Cursor::new(bytes)).err().unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(Section::Pixels));
+}
+
+/// md2 without geometry and one skin name
+fn md2_with_skin(name: &str) -> md2::Model {
+    let mut buf = md2_header();
+    set_i32(&mut buf, 20, 1); // num_skins
+    push_name(&mut buf, name, 64);
+
+    md2::Model::from_reader(&mut Cursor::new(buf)).unwrap()
+}
+
+#[test]
+fn md2_skin_from_base_dir() {
+    let base_dir = std::env::temp_dir().join(format!("model-reader-pcx-{}", std::process::id()));
+    std::fs::create_dir_all(base_dir.join("models/test")).unwrap();
+    std::fs::write(
+        base_dir.join("models/test/skin.pcx"),
+        pcx(&[0xc2, 7, 8, 0, 0xc3, 9, 0]),
+    )
+    .unwrap();
+
+    let model = md2_with_skin("models/test/skin.pcx");
+    let image = load_md2_skin(&model, 0, &base_dir).unwrap();
+    assert_eq!((image.width, image.height), (3, 2));
+    assert_eq!(image.data[0..4], [7, 7, 7, 255]);
+    assert_eq!(image.data[8..12], [8, 8, 8, 255]);
+    assert_eq!(image.data[20..24], [9, 9, 9, 255]);
+
+    assert_eq!(
+        load_md2_skin(&model, 1, &base_dir).err().unwrap().kind(),
+        &ErrorKind::InvalidIndex { index: 1, len: 1 }
+    );
+    let missing = md2_with_skin("models/test/missing.pcx");
+    assert!(load_md2_skin(&missing, 0, &base_dir).is_err());
+
+    std::fs::remove_dir_all(&base_dir).unwrap();
+}
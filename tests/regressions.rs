@@ -0,0 +1,58 @@
+extern crate model_reader;
+
+use model_reader::flat_model::FlatModel;
+use model_reader::pcx::Pcx;
+use model_reader::{load_any, mdl, spr, AnyModel, LoadOptions};
+use std::io::{sink, Cursor};
+use std::path::Path;
+
+/// runs a file through the same code as the fuzz targets.
+/// errors are fine, panics are not
+fn check(data: &[u8]) {
+    let _ = Pcx::from_reader(&mut Cursor::new(data)).map(|pcx| pcx.to_rgba());
+    let _ = mdl::Model::from_reader(&mut Cursor::new(data)).map(|model| model.validate());
+    let _ = spr::Sprite::from_reader(&mut Cursor::new(data)).map(|sprite| sprite.validate());
+
+    let model = match load_any(&mut Cursor::new(data)) {
+        Ok(model) => model,
+        Err(_) => return,
+    };
+    let issues = match &model {
+        AnyModel::Mdl(model) => model.validate(),
+        AnyModel::Md2(model) => model.validate(),
+        AnyModel::Md3(model) => model.validate(),
+    };
+    if !issues.is_empty() {
+        return;
+    }
+
+    let options = LoadOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(model_reader::load_any_with(&mut Cursor::new(data), &options).is_ok());
+
+    FlatModel::from_model(&model)
+        .write_json(&mut sink())
+        .unwrap();
+    if let AnyModel::Mdl(model) = &model {
+        model.write_json(&mut sink()).unwrap();
+    }
+}
+
+/// every file in tests/regressions is an input that once crashed
+/// a fuzz target. add new crashes there with a descriptive name
+#[test]
+fn regressions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/regressions");
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        // the panic message of check doesn't say which file it was
+        let result = std::panic::catch_unwind(|| check(&data));
+        assert!(result.is_ok(), "{} failed", path.display());
+        count += 1;
+    }
+    assert!(count > 0);
+}
@@ -0,0 +1,134 @@
+extern crate model_reader;
+
+use model_reader::palette::Palette;
+use model_reader::spr::*;
+use model_reader::validation::Issue;
+use model_reader::{ErrorKind, LoadOptions, Section};
+use std::io::Cursor;
+
+mod common;
+use common::{push_f32, push_i32};
+
+fn i32s(out: &mut Vec<u8>, values: &[i32]) {
+    for v in values {
+        push_i32(out, *v);
+    }
+}
+
+fn frame(out: &mut Vec<u8>, origin: [i32; 2], pixels: &[u8]) {
+    i32s(out, &[origin[0], origin[1], 2, 1]);
+    out.extend_from_slice(pixels);
+}
+
+/// a sprite of type typ with a single frame and a group of two
+fn sprite(typ: i32) -> Vec<u8> {
+    let mut data = Vec::new();
+    i32s(&mut data, &[HEADER_IDENT, HEADER_VERSION, typ]);
+    push_f32(&mut data, 8.0);
+    i32s(&mut data, &[2, 1, 2]);
+    push_f32(&mut data, 0.0);
+    i32s(&mut data, &[1]);
+
+    i32s(&mut data, &[0]);
+    frame(&mut data, [-1, 1], &[1, TRANSPARENT]);
+
+    i32s(&mut data, &[1, 2]);
+    push_f32(&mut data, 0.1);
+    push_f32(&mut data, 0.3);
+    frame(&mut data, [0, 0], &[2, 3]);
+    frame(&mut data, [-2, 4], &[4, 5]);
+    data
+}
+
+#[test]
+fn spr_read() {
+    let sprite = Sprite::from_reader(&mut Cursor::new(sprite(1))).unwrap();
+    assert_eq!(sprite.header.typ, Orientation::FacingUpright);
+    assert_eq!(sprite.header.bounding_radius, 8.0);
+    assert_eq!(sprite.frames.len(), 2);
+    assert!(sprite.validate().is_empty());
+
+    match &sprite.frames[0] {
+        Frame::Simple(frame) => {
+            assert_eq!(frame.origin, [-1, 1]);
+            assert_eq!((frame.width, frame.height), (2, 1));
+            assert_eq!(frame.data, vec![1, TRANSPARENT]);
+        }
+        Frame::Group(_) => panic!("expected a simple frame"),
+    }
+    match &sprite.frames[1] {
+        Frame::Group(group) => {
+            assert_eq!(group.time, vec![0.1, 0.3]);
+            assert_eq!(group.frames.len(), 2);
+            assert_eq!(group.frames[1].origin, [-2, 4]);
+        }
+        Frame::Simple(_) => panic!("expected a group"),
+    }
+
+    assert_eq!(sprite.frames[1].frame_at(0.05).unwrap().data, vec![2, 3]);
+    assert_eq!(sprite.frames[1].frame_at(0.2).unwrap().data, vec![4, 5]);
+    assert_eq!(sprite.frames[1].frame_at(0.35).unwrap().data, vec![2, 3]);
+    assert_eq!(sprite.syncbase(0.5), 0.5);
+}
+
+#[test]
+fn spr_rgba() {
+    let sprite = Sprite::from_reader(&mut Cursor::new(sprite(0))).unwrap();
+    let palette = Palette::default();
+
+    let (image, origin) = sprite.rgba_at(0, 0.0, &palette).unwrap();
+    assert_eq!(origin, [-1, 1]);
+    assert_eq!((image.width, image.height), (2, 1));
+    let rgb = palette.color(1);
+    assert_eq!(image.data, vec![rgb[0], rgb[1], rgb[2], 255, 0, 0, 0, 0]);
+
+    let (image, origin) = sprite.rgba_at(1, 0.2, &palette).unwrap();
+    assert_eq!(origin, [-2, 4]);
+    assert_eq!(&image.data[0..3], &palette.color(4));
+    assert!(sprite.rgba_at(2, 0.0, &palette).is_none());
+
+    // missing pixels are transparent
+    let frame = SimpleFrame {
+        origin: [0, 0],
+        width: 2,
+        height: 2,
+        data: vec![1],
+    };
+    let image = frame.rgba(&palette);
+    assert_eq!(image.data.len(), 2 * 2 * 4);
+    assert_eq!(image.data[4..], [0; 12]);
+}
+
+#[test]
+fn spr_errors() {
+    let err = Sprite::from_reader(&mut Cursor::new(sprite(7)))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Unsupported);
+    assert_eq!(err.section(), Some(Section::Header));
+
+    let mut data = sprite(0);
+    data.truncate(data.len() - 1);
+    let err = Sprite::from_reader(&mut Cursor::new(data)).err().unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Truncated);
+    assert_eq!(err.section(), Some(Section::Frames));
+
+    // an empty group
+    let mut data = sprite(0);
+    let len = data.len();
+    data.truncate(len - 2 * (16 + 2) - 8 - 4);
+    i32s(&mut data, &[0]);
+    let strict = LoadOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let err = Sprite::from_reader_with(&mut Cursor::new(data), &strict)
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Invalid {
+            issues: vec![Issue::EmptyGroup { frame: 1 }]
+        }
+    );
+}